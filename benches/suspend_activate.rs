@@ -0,0 +1,45 @@
+//! Suspending and activating raw mode, with the attributes cached and without.
+//!
+//! With caching, each of the two is a single `tcsetattr`; without it, activating reads the
+//! attributes with `tcgetattr` first.
+
+use std::{fs::File, os::fd::FromRawFd, ptr};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use termion_raw2::IntoRawMode;
+
+/// Open a PTY, returning the master and the slave.
+fn pty() -> (File, File) {
+    let (mut master, mut slave) = (0, 0);
+    let ret = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            ptr::null_mut(),
+            ptr::null(),
+            ptr::null(),
+        )
+    };
+    assert_eq!(ret, 0, "openpty failed");
+    unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) }
+}
+
+fn suspend_activate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("suspend_activate");
+    for caching in [true, false] {
+        let (_master, tty) = pty();
+        let mut tty = tty.into_raw_mode().unwrap();
+        tty.set_attr_caching(caching);
+        let name = if caching { "cached" } else { "uncached" };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                tty.suspend_raw_mode().unwrap();
+                tty.activate_raw_mode().unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, suspend_activate);
+criterion_main!(benches);
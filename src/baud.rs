@@ -0,0 +1,184 @@
+//! Typed baud rates.
+
+use std::{
+    io::{self, Write},
+    os::fd::AsFd,
+};
+
+use crate::sys::attr::{output_speed, set_speeds};
+use crate::RawTerminal;
+
+macro_rules! baud_rates {
+    ($($(#[$cfg:meta])* $variant:ident = $rate:literal,)*) => {
+        /// A line speed, as set with [`RawTerminal::set_baud_rate`].
+        ///
+        /// The named rates are those with a `B*` constant on the platform. Any rate can be
+        /// represented as `Other`, but only Linux (through `termios2`) and the BSDs and macOS
+        /// (whose `speed_t` is the rate itself) can set one that isn't named.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum BaudRate {
+            $(
+                #[doc = concat!(stringify!($rate), " baud.")]
+                $(#[$cfg])*
+                $variant,
+            )*
+            /// Any other rate, in baud.
+            Other(u32),
+        }
+
+        impl BaudRate {
+            /// The rate for `rate` baud, one of the named ones if there is one.
+            pub fn from_u32(rate: u32) -> Self {
+                match rate {
+                    $($(#[$cfg])* $rate => BaudRate::$variant,)*
+                    _ => BaudRate::Other(rate),
+                }
+            }
+
+            /// The rate in baud.
+            pub fn as_u32(self) -> u32 {
+                match self {
+                    $($(#[$cfg])* BaudRate::$variant => $rate,)*
+                    BaudRate::Other(rate) => rate,
+                }
+            }
+
+            /// Convert a `speed_t` as returned by `cfgetospeed`.
+            ///
+            /// Returns `None` for a value that isn't one of the `B*` constants on Linux, e.g.
+            /// `BOTHER`.
+            pub fn from_speed_t(speed: libc::speed_t) -> Option<Self> {
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                return match speed {
+                    $($(#[$cfg])* libc::$variant => Some(BaudRate::$variant),)*
+                    _ => None,
+                };
+                #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                return u32::try_from(speed).ok().map(BaudRate::from_u32);
+            }
+
+            /// Convert to a `speed_t` for `cfsetospeed`.
+            ///
+            /// Returns `None` for a rate that has no `B*` constant on Linux.
+            pub fn to_speed_t(self) -> Option<libc::speed_t> {
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                return match BaudRate::from_u32(self.as_u32()) {
+                    $($(#[$cfg])* BaudRate::$variant => Some(libc::$variant),)*
+                    BaudRate::Other(_) => None,
+                };
+                #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                return Some(self.as_u32() as libc::speed_t);
+            }
+        }
+    };
+}
+
+baud_rates! {
+    B0 = 0,
+    B50 = 50,
+    B75 = 75,
+    B110 = 110,
+    B134 = 134,
+    B150 = 150,
+    B200 = 200,
+    B300 = 300,
+    B600 = 600,
+    B1200 = 1200,
+    B1800 = 1800,
+    B2400 = 2400,
+    B4800 = 4800,
+    B9600 = 9600,
+    B19200 = 19200,
+    B38400 = 38400,
+    B57600 = 57600,
+    B115200 = 115200,
+    B230400 = 230400,
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    B460800 = 460800,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    B500000 = 500000,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    B576000 = 576000,
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    B921600 = 921600,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    B1000000 = 1000000,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    B1152000 = 1152000,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    B1500000 = 1500000,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    B2000000 = 2000000,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    B2500000 = 2500000,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    B3000000 = 3000000,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    B3500000 = 3500000,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    B4000000 = 4000000,
+}
+
+impl<W: Write + AsFd> RawTerminal<W> {
+    /// The output speed of the terminal
+    ///
+    /// On Linux, a speed set with [`set_custom_baud`](Self::set_custom_baud) is read back as
+    /// well, as [`BaudRate::Other`] unless it is one of the named rates.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{BaudRate, IntoRawMode};
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// tty.set_baud_rate(BaudRate::B9600)?;
+    /// assert_eq!(tty.baud_rate()?, BaudRate::B9600);
+    /// assert_eq!(tty.baud_rate()?.as_u32(), 9600);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn baud_rate(&self) -> io::Result<BaudRate> {
+        let speed = output_speed(self.control_fd())?;
+        if let Some(rate) = BaudRate::from_speed_t(speed) {
+            return Ok(rate);
+        }
+        #[cfg(all(
+            target_os = "linux",
+            not(any(target_arch = "powerpc", target_arch = "powerpc64"))
+        ))]
+        if speed == libc::BOTHER {
+            return crate::sys::attr::custom_baud(self.control_fd()).map(BaudRate::from_u32);
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unknown terminal speed",
+        ))
+    }
+
+    /// Set the input and output speed
+    ///
+    /// A rate without a `B*` constant is set with [`set_custom_baud`](Self::set_custom_baud) on
+    /// Linux, and fails with [`InvalidInput`](io::ErrorKind::InvalidInput) on platforms where
+    /// that isn't available. The previous speed is restored on drop along with everything else.
+    pub fn set_baud_rate(&mut self, rate: BaudRate) -> io::Result<()> {
+        match rate.to_speed_t() {
+            Some(speed) => self.try_modify_attrs(|ios| set_speeds(ios, speed)),
+            #[cfg(all(
+                target_os = "linux",
+                not(any(target_arch = "powerpc", target_arch = "powerpc64"))
+            ))]
+            None => self.set_custom_baud(rate.as_u32()),
+            #[cfg(not(all(
+                target_os = "linux",
+                not(any(target_arch = "powerpc", target_arch = "powerpc64"))
+            )))]
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "baud rate not supported on this platform",
+            )),
+        }
+    }
+}
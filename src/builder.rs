@@ -0,0 +1,307 @@
+//! Configuring raw mode in more detail than [`IntoRawMode::into_raw_mode`](crate::IntoRawMode).
+
+use std::{
+    io::{self, Write},
+    os::fd::AsFd,
+    time::Duration,
+};
+
+use crate::sys::attr::raw_terminal_attr;
+use crate::{RawTerminal, SetMode, Termios};
+
+/// How reads from the terminal behave, set through `VMIN` and `VTIME`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadMode {
+    /// Block until at least one byte is available (`VMIN = 1`, `VTIME = 0`).
+    #[default]
+    Blocking,
+    /// Wait up to the given duration for a byte, returning 0 bytes on timeout (`VMIN = 0`).
+    ///
+    /// `VTIME` counts in tenths of a second, so the duration is rounded up to that and capped at
+    /// 25.5 seconds.
+    Timeout(Duration),
+    /// Return immediately with whatever is available, possibly nothing (`VMIN = 0`,
+    /// `VTIME = 0`).
+    NonBlocking,
+}
+
+impl ReadMode {
+    pub(crate) fn apply_to(self, ios: &mut Termios) {
+        let (min, time) = match self {
+            ReadMode::Blocking => (1, 0),
+            ReadMode::Timeout(timeout) => {
+                let tenths = timeout.as_millis().div_ceil(100).clamp(1, 255);
+                (0, tenths as libc::cc_t)
+            }
+            ReadMode::NonBlocking => (0, 0),
+        };
+        ios.c_cc[libc::VMIN] = min;
+        ios.c_cc[libc::VTIME] = time;
+    }
+}
+
+/// The raw mode options as plain data, for [`IntoRawMode::into_raw_mode_with_config`].
+///
+/// Each `true` re-enables the corresponding behavior on top of plain raw mode, just like the
+/// methods of [`RawModeBuilder`] with the same names. The default is plain raw mode.
+///
+/// ```rust,no_run
+/// use termion_raw2::{IntoRawMode, RawConfig};
+/// use std::io::stdout;
+///
+/// let config = RawConfig {
+///     signals: true,
+///     ..RawConfig::default()
+/// };
+/// let stdout = stdout().into_raw_mode_with_config(config)?;
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// [`IntoRawMode::into_raw_mode_with_config`]: crate::IntoRawMode::into_raw_mode_with_config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RawConfig {
+    /// Keep echoing the input (`ECHO`).
+    pub echo: bool,
+    /// Keep line buffering the input (`ICANON`).
+    pub canonical: bool,
+    /// Keep generating signals for Ctrl-C, Ctrl-Z and Ctrl-\\ (`ISIG`).
+    pub signals: bool,
+    /// Keep software flow control with Ctrl-S and Ctrl-Q (`IXON`/`IXOFF`).
+    pub flow_control: bool,
+    /// How reads from the terminal behave.
+    pub read_mode: ReadMode,
+    /// Keep processing the output, e.g. translating `\n` to `\r\n` (`OPOST`/`ONLCR`).
+    pub output_processing: bool,
+}
+
+impl RawConfig {
+    pub(crate) fn apply_to(self, ios: &mut Termios) {
+        raw_terminal_attr(ios);
+        if self.echo {
+            ios.c_lflag |= libc::ECHO;
+        }
+        if self.canonical {
+            ios.c_lflag |= libc::ICANON;
+        }
+        if self.signals {
+            ios.c_lflag |= libc::ISIG;
+        }
+        if self.flow_control {
+            ios.c_iflag |= libc::IXON | libc::IXOFF;
+        }
+        if self.output_processing {
+            ios.c_oflag |= libc::OPOST | libc::ONLCR;
+        }
+        self.read_mode.apply_to(ios);
+    }
+}
+
+type Configure = Box<dyn FnOnce(&mut Termios)>;
+
+/// A builder for raw mode with some of the usual behavior kept.
+///
+/// Starts from the same attributes as [`into_raw_mode`](crate::IntoRawMode::into_raw_mode) and
+/// re-enables whatever is asked for. All changes are computed in memory, so applying the builder
+/// reads and writes the attributes exactly once each.
+///
+/// ```rust,no_run
+/// use termion_raw2::{RawModeBuilder, ReadMode};
+/// use std::{io::stdout, time::Duration};
+///
+/// let stdout = RawModeBuilder::new()
+///     .signals(true)
+///     .read_mode(ReadMode::Timeout(Duration::from_millis(100)))
+///     .apply_batched(stdout())?;
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Default)]
+pub struct RawModeBuilder {
+    config: RawConfig,
+    keep_istrip: bool,
+    keep_parmrk: bool,
+    tostop: Option<bool>,
+    set_mode: SetMode,
+    configure: Vec<Configure>,
+}
+
+impl RawModeBuilder {
+    /// Create a builder for plain raw mode.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep echoing the input (`ECHO`).
+    pub fn echo(mut self, enabled: bool) -> Self {
+        self.config.echo = enabled;
+        self
+    }
+
+    /// Keep line buffering the input (`ICANON`).
+    pub fn canonical(mut self, enabled: bool) -> Self {
+        self.config.canonical = enabled;
+        self
+    }
+
+    /// Keep generating signals for Ctrl-C, Ctrl-Z and Ctrl-\\ (`ISIG`).
+    pub fn signals(mut self, enabled: bool) -> Self {
+        self.config.signals = enabled;
+        self
+    }
+
+    /// Keep software flow control with Ctrl-S and Ctrl-Q (`IXON`/`IXOFF`).
+    pub fn flow_control(mut self, enabled: bool) -> Self {
+        self.config.flow_control = enabled;
+        self
+    }
+
+    /// Keep processing the output, e.g. translating `\n` to `\r\n` (`OPOST`/`ONLCR`).
+    pub fn output_processing(mut self, enabled: bool) -> Self {
+        self.config.output_processing = enabled;
+        self
+    }
+
+    /// Keep stripping input bytes to 7 bits (`ISTRIP`), if it is on.
+    ///
+    /// `cfmakeraw` turns this off, which is what 8-bit clean terminals want. On 7-bit serial
+    /// links the 8th bit may carry parity or noise, and should keep being stripped.
+    pub fn keep_istrip(mut self, enabled: bool) -> Self {
+        self.keep_istrip = enabled;
+        self
+    }
+
+    /// Keep marking bytes with parity errors in the input (`PARMRK`), if it is on.
+    ///
+    /// `cfmakeraw` turns this off. With parity checking on (`INPCK`), marking lets programs on
+    /// error-detecting links tell bad bytes apart, see
+    /// [`InputFlags::PARMRK`](crate::InputFlags::PARMRK).
+    pub fn keep_parmrk(mut self, enabled: bool) -> Self {
+        self.keep_parmrk = enabled;
+        self
+    }
+
+    /// Turn stopping background processes that write to the terminal on or off (`TOSTOP`).
+    ///
+    /// Raw mode doesn't change this flag by itself, so without this option it stays as it was.
+    /// It is reverted on drop along with everything else. See
+    /// [`LocalFlags::TOSTOP`](crate::LocalFlags::TOSTOP).
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::RawModeBuilder;
+    ///
+    /// let tty = RawModeBuilder::new().tostop(true).apply_batched(tty)?;
+    /// # let mut ios = unsafe { std::mem::zeroed() };
+    /// # assert_eq!(unsafe { libc::tcgetattr(std::os::fd::AsRawFd::as_raw_fd(&tty), &mut ios) }, 0);
+    /// # assert_ne!(ios.c_lflag & libc::TOSTOP, 0);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn tostop(mut self, enabled: bool) -> Self {
+        self.tostop = Some(enabled);
+        self
+    }
+
+    /// Set how reads from the terminal behave.
+    pub fn read_mode(mut self, mode: ReadMode) -> Self {
+        self.config.read_mode = mode;
+        self
+    }
+
+    /// Set when the new attributes take effect, e.g. [`SetMode::Flush`] to discard typed-ahead
+    /// input.
+    pub fn set_mode(mut self, mode: SetMode) -> Self {
+        self.set_mode = mode;
+        self
+    }
+
+    /// Make an arbitrary change to the attributes.
+    ///
+    /// Closures run in the order they were added, after all other options are applied.
+    pub fn configure(mut self, f: impl FnOnce(&mut Termios) + 'static) -> Self {
+        self.configure.push(Box::new(f));
+        self
+    }
+
+    /// Switch `writer` to the configured mode with a single read and a single write of the
+    /// terminal attributes.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// # let other = tty.try_clone()?;
+    /// use termion_raw2::{capture_state, IntoRawMode, LocalFlags, RawModeBuilder};
+    ///
+    /// let tty = RawModeBuilder::new().signals(true).flow_control(true).apply_batched(tty)?;
+    /// let batched = capture_state(&tty)?;
+    /// drop(tty);
+    ///
+    /// // The same changes made one at a time, reading and writing the attributes once each
+    /// let mut other = other.into_raw_mode()?;
+    /// other.modify_local_flags(|flags| flags.insert(LocalFlags::ISIG))?;
+    /// other.set_flow_control(true)?;
+    /// assert_eq!(capture_state(&other)?, batched);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn apply_batched<W: Write + AsFd>(self, writer: W) -> io::Result<RawTerminal<W>> {
+        let mode = self.set_mode;
+        RawTerminal::enter(writer, None, mode, |ios| self.apply_to(ios))
+    }
+
+    fn apply_to(self, ios: &mut Termios) {
+        let istrip = ios.c_iflag & libc::ISTRIP;
+        let parmrk = ios.c_iflag & libc::PARMRK;
+        self.config.apply_to(ios);
+        if self.keep_istrip {
+            ios.c_iflag |= istrip;
+        }
+        if self.keep_parmrk {
+            ios.c_iflag |= parmrk;
+        }
+        match self.tostop {
+            Some(true) => ios.c_lflag |= libc::TOSTOP,
+            Some(false) => ios.c_lflag &= !libc::TOSTOP,
+            None => {}
+        }
+        for f in self.configure {
+            f(ios);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use crate::sys::sim::call_counts;
+    use crate::{IntoRawMode, LocalFlags, RawModeBuilder};
+
+    /// Any fd serves as a terminal, as the attributes are simulated in unit tests
+    fn terminal() -> File {
+        File::options().write(true).open("/dev/null").unwrap()
+    }
+
+    #[test]
+    fn apply_batched_reads_and_sets_once() {
+        let (gets, sets) = call_counts();
+        let _tty = RawModeBuilder::new()
+            .signals(true)
+            .flow_control(true)
+            .apply_batched(terminal())
+            .unwrap();
+        assert_eq!(call_counts(), (gets + 1, sets + 1));
+
+        // The same changes made one at a time read and set the attributes once each
+        let (gets, sets) = call_counts();
+        let mut tty = terminal().into_raw_mode().unwrap();
+        tty.modify_local_flags(|flags| flags.insert(LocalFlags::ISIG))
+            .unwrap();
+        tty.set_flow_control(true).unwrap();
+        assert_eq!(call_counts(), (gets + 3, sets + 3));
+    }
+}
@@ -0,0 +1,76 @@
+//! Typed errors for entering raw mode.
+
+use std::{error, fmt, io};
+
+/// Why switching to raw mode failed.
+///
+/// Converts to and from [`io::Error`]; the specific variants correspond to the `errno` values
+/// listed on them, except [`NotHonored`](Self::NotHonored), which converts to an error of kind
+/// [`Other`](io::ErrorKind::Other).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RawModeError {
+    /// The fd doesn't refer to a terminal (`ENOTTY`).
+    NotATty,
+    /// The fd isn't valid (`EBADF`).
+    BadFd,
+    /// The call was interrupted by a signal and can be retried (`EINTR`).
+    Interrupted,
+    /// Changing the terminal attributes isn't allowed (`EPERM`/`EACCES`).
+    PermissionDenied,
+    /// The attributes were set without an error, but reading them back showed the terminal
+    /// isn't in raw mode, as happens with some broken or remote terminals. Only returned by
+    /// [`RawTerminal::try_new`](crate::RawTerminal::try_new).
+    NotHonored,
+    /// Any other error.
+    Other(io::Error),
+}
+
+impl From<io::Error> for RawModeError {
+    fn from(err: io::Error) -> Self {
+        match err.raw_os_error() {
+            Some(libc::ENOTTY) => RawModeError::NotATty,
+            Some(libc::EBADF) => RawModeError::BadFd,
+            Some(libc::EINTR) => RawModeError::Interrupted,
+            Some(libc::EPERM | libc::EACCES) => RawModeError::PermissionDenied,
+            _ => RawModeError::Other(err),
+        }
+    }
+}
+
+impl From<RawModeError> for io::Error {
+    fn from(err: RawModeError) -> Self {
+        match err {
+            RawModeError::NotATty => io::Error::from_raw_os_error(libc::ENOTTY),
+            RawModeError::BadFd => io::Error::from_raw_os_error(libc::EBADF),
+            RawModeError::Interrupted => io::Error::from_raw_os_error(libc::EINTR),
+            RawModeError::PermissionDenied => io::Error::from_raw_os_error(libc::EPERM),
+            err @ RawModeError::NotHonored => io::Error::other(err),
+            RawModeError::Other(err) => err,
+        }
+    }
+}
+
+impl fmt::Display for RawModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RawModeError::NotATty => f.write_str("not a terminal"),
+            RawModeError::BadFd => f.write_str("bad file descriptor"),
+            RawModeError::Interrupted => f.write_str("interrupted by a signal"),
+            RawModeError::PermissionDenied => {
+                f.write_str("permission denied to change terminal attributes")
+            }
+            RawModeError::NotHonored => f.write_str("the terminal didn't switch to raw mode"),
+            RawModeError::Other(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for RawModeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RawModeError::Other(err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Reading single escape sequences, e.g. replies to terminal queries.
+
+use std::{
+    io::{self, Read, Write},
+    os::fd::AsFd,
+    time::{Duration, Instant},
+};
+
+use crate::{RawInput, RawTerminal};
+
+pub(crate) const ESC: u8 = 0x1b;
+
+impl<W: Write + AsFd> RawTerminal<W> {
+    /// Read exactly one escape sequence and return its bytes, including the leading `ESC`.
+    ///
+    /// Meant for reading the replies to queries like the cursor position (`ESC [ 6 n`) or the
+    /// background color (`ESC ] 11 ; ? BEL`). The end of the sequence is determined by the
+    /// ECMA-48 grammar:
+    ///
+    /// - CSI (`ESC [`) ends with its final byte, after any parameter and intermediate bytes.
+    /// - OSC (`ESC ]`) ends with the string terminator `ESC \` or `BEL`. DCS (`ESC P`), SOS
+    ///   (`ESC X`), PM (`ESC ^`) and APC (`ESC _`) end with `ESC \` only.
+    /// - SS2 (`ESC N`) and SS3 (`ESC O`) end after one more byte.
+    /// - Other sequences end with the first byte that isn't an intermediate byte, e.g.
+    ///   `ESC ( B` or `ESC 7`.
+    ///
+    /// A byte that can't be part of the sequence ends it early and is pushed back to `input`,
+    /// so it is read next. In particular, an `ESC` within a string that isn't followed by `\`
+    /// starts the next sequence.
+    ///
+    /// `timeout` covers the whole sequence. Returns `None` if no byte arrives in time, or if
+    /// the first byte isn't `ESC`, in which case it is pushed back. Fails with
+    /// [`TimedOut`](io::ErrorKind::TimedOut) if the sequence doesn't complete in time (the bytes
+    /// read so far are lost), and with [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) if the
+    /// input is closed.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{IntoRawMode, RawInput};
+    /// use std::time::Duration;
+    ///
+    /// let mut input = RawInput::new(tty.try_clone()?);
+    /// let mut tty = tty.into_raw_mode()?;
+    /// let timeout = Duration::from_millis(100);
+    /// # master.write_all(b"\x1b[12;40R\x1b]11;rgb:0000/0000/0000\x1b\\\x1b]2;title\x07")?;
+    /// assert_eq!(tty.read_escape_sequence(&mut input, timeout)?.unwrap(), b"\x1b[12;40R");
+    /// assert_eq!(
+    ///     tty.read_escape_sequence(&mut input, timeout)?.unwrap(),
+    ///     b"\x1b]11;rgb:0000/0000/0000\x1b\\"
+    /// );
+    /// assert_eq!(tty.read_escape_sequence(&mut input, timeout)?.unwrap(), b"\x1b]2;title\x07");
+    /// assert_eq!(tty.read_escape_sequence(&mut input, timeout)?, None);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn read_escape_sequence<R: Read + AsFd>(
+        &mut self,
+        input: &mut RawInput<R>,
+        timeout: Duration,
+    ) -> io::Result<Option<Vec<u8>>> {
+        read_escape_sequence(input, Instant::now() + timeout)
+    }
+}
+
+pub(crate) fn read_escape_sequence<R: Read + AsFd>(
+    input: &mut RawInput<R>,
+    deadline: Instant,
+) -> io::Result<Option<Vec<u8>>> {
+    match next_byte(input, deadline)? {
+        None => return Ok(None),
+        Some(ESC) => {}
+        Some(byte) => {
+            input.unget(byte);
+            return Ok(None);
+        }
+    }
+
+    let mut seq = vec![ESC];
+    let next = |input: &mut RawInput<R>| {
+        next_byte(input, deadline)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "incomplete escape sequence"))
+    };
+
+    let kind = next(input)?;
+    seq.push(kind);
+    match kind {
+        // CSI: parameter and intermediate bytes, then a final byte
+        b'[' => loop {
+            match next(input)? {
+                byte @ 0x20..=0x3f => seq.push(byte),
+                byte @ 0x40..=0x7e => {
+                    seq.push(byte);
+                    break;
+                }
+                byte => {
+                    input.unget(byte);
+                    break;
+                }
+            }
+        },
+        // Strings: OSC, DCS, SOS, PM and APC
+        b']' | b'P' | b'X' | b'^' | b'_' => loop {
+            match next(input)? {
+                0x07 if kind == b']' => {
+                    seq.push(0x07);
+                    break;
+                }
+                ESC => match next(input)? {
+                    b'\\' => {
+                        seq.extend_from_slice(b"\x1b\\");
+                        break;
+                    }
+                    byte => {
+                        input.unget(byte);
+                        input.unget(ESC);
+                        break;
+                    }
+                },
+                byte => seq.push(byte),
+            }
+        },
+        // SS2 and SS3 apply to the next byte
+        b'N' | b'O' => seq.push(next(input)?),
+        // Intermediate bytes, then a final byte
+        0x20..=0x2f => loop {
+            match next(input)? {
+                byte @ 0x20..=0x2f => seq.push(byte),
+                byte @ 0x30..=0x7e => {
+                    seq.push(byte);
+                    break;
+                }
+                byte => {
+                    input.unget(byte);
+                    break;
+                }
+            }
+        },
+        0x30..=0x7e => {}
+        // Not an escape sequence after all, just a lone ESC
+        byte => {
+            seq.pop();
+            input.unget(byte);
+        }
+    }
+    Ok(Some(seq))
+}
+
+/// Wait for and read the next byte, returning `None` if `deadline` passes first.
+pub(crate) fn next_byte<R: Read + AsFd>(
+    input: &mut RawInput<R>,
+    deadline: Instant,
+) -> io::Result<Option<u8>> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if !input.wait_readable(Some(remaining))? {
+        return Ok(None);
+    }
+    match input.read_byte()? {
+        Some(byte) => Ok(Some(byte)),
+        None => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
@@ -0,0 +1,246 @@
+//! Reading keys, mouse reports and other input as one stream of events.
+
+use std::{
+    io::{self, Read, Write},
+    os::fd::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd},
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use crate::keys::{read_event, ESC_TIMEOUT};
+use crate::sys::pipe::pipe;
+use crate::sys::poll::wait_readable_either;
+use crate::sys::signal::{preserving_errno, set_handler};
+use crate::sys::tty::win_size;
+use crate::{Key, MouseEvent, RawInput, RawTerminal, WinSize};
+
+/// An input event.
+///
+/// More kinds of events may be added in the future, so matches need a wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Event {
+    /// A key press.
+    Key(Key),
+    /// A mouse report.
+    Mouse(MouseEvent),
+    /// The terminal was resized, to the given size.
+    ///
+    /// Only reported by [`events`].
+    Resize(WinSize),
+    /// Text pasted in bracketed paste mode
+    /// ([`BRACKETED_PASTE_ON`](crate::sequences::BRACKETED_PASTE_ON)), without the markers.
+    ///
+    /// Invalid UTF-8 is replaced with `U+FFFD`.
+    Paste(String),
+    /// The terminal gained focus, if focus reporting is enabled
+    /// ([`FOCUS_REPORTING_ON`](crate::sequences::FOCUS_REPORTING_ON)).
+    FocusGained,
+    /// The terminal lost focus, if focus reporting is enabled.
+    FocusLost,
+}
+
+impl Event {
+    /// The key, if this is an [`Event::Key`].
+    pub fn as_key(&self) -> Option<&Key> {
+        match self {
+            Event::Key(key) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// The mouse event, if this is an [`Event::Mouse`].
+    pub fn as_mouse(&self) -> Option<&MouseEvent> {
+        match self {
+            Event::Mouse(event) => Some(event),
+            _ => None,
+        }
+    }
+}
+
+impl<W: Write + AsFd> RawTerminal<W> {
+    /// Block until a key is pressed or a mouse report arrives, and return it.
+    ///
+    /// This is [`read_key`](Self::read_key), with mouse reports parsed as by
+    /// [`parse_mouse`](crate::parse_mouse), and pastes and focus changes reported as their own
+    /// events. A mouse report that can't be parsed is returned as [`Key::Unknown`]. Resizes are
+    /// only reported by [`events`].
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{Event, IntoRawMode, Key, MouseButton, MouseEventKind, RawInput};
+    ///
+    /// let mut input = RawInput::new(tty.try_clone()?);
+    /// let mut tty = tty.into_raw_mode()?;
+    /// # master.write_all(b"\x1b[<2;3;4mq\x1b[M\x20\x21\x22")?;
+    /// let Event::Mouse(event) = tty.read_event(&mut input)? else { panic!() };
+    /// assert_eq!((event.button, event.kind), (Some(MouseButton::Right), MouseEventKind::Release));
+    /// assert_eq!((event.x, event.y), (3, 4));
+    /// assert_eq!(tty.read_event(&mut input)?, Event::Key(Key::Char('q')));
+    /// let Event::Mouse(event) = tty.read_event(&mut input)? else { panic!() };
+    /// assert_eq!((event.button, event.kind), (Some(MouseButton::Left), MouseEventKind::Press));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn read_event<R: Read + AsFd>(&mut self, input: &mut RawInput<R>) -> io::Result<Event> {
+        read_event(input, self.esc_timeout).map(|(event, _)| event)
+    }
+}
+
+/// Read events from `reader`, a terminal in raw mode, including resizes.
+///
+/// Events are parsed as by [`RawTerminal::read_event`]. In addition, when the process receives
+/// `SIGWINCH`, the new size of the terminal is yielded as [`Event::Resize`]; several resizes in
+/// quick succession are reported once. The first call installs a `SIGWINCH` handler that is
+/// never uninstalled. A handler installed before it is replaced, and only one `Events` receives
+/// each resize.
+///
+/// Errors are yielded as items, and reading continues with the next call. The iterator ends
+/// when the input is closed, after yielding whatever arrived before:
+///
+/// - An escape sequence cut off by the end of input is yielded as [`Key::Unknown`] with the
+///   bytes read, or as [`Key::Esc`] if it's a lone `ESC`.
+/// - A paste cut off by the end of input is yielded as [`Event::Paste`] with the text read.
+///
+/// ```rust
+/// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # assert_eq!(ret, 0);
+/// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// use termion_raw2::{events, Event, IntoRawMode, Key};
+///
+/// let mut events = events(tty.try_clone()?);
+/// let tty = tty.into_raw_mode()?;
+/// # master.write_all(b"a\x1b[200~hi\x1b[A\x1b[201~\x1b[I")?;
+/// assert_eq!(events.next().unwrap()?, Event::Key(Key::Char('a')));
+/// assert_eq!(events.next().unwrap()?, Event::Paste("hi\x1b[A".into()));
+/// assert_eq!(events.next().unwrap()?, Event::FocusGained);
+/// # let size = libc::winsize { ws_row: 30, ws_col: 100, ws_xpixel: 0, ws_ypixel: 0 };
+/// # assert_eq!(unsafe { libc::ioctl(std::os::fd::AsRawFd::as_raw_fd(&master), libc::TIOCSWINSZ, &size) }, 0);
+/// # unsafe { libc::raise(libc::SIGWINCH) };
+/// let Event::Resize(size) = events.next().unwrap()? else { panic!() };
+/// assert_eq!((size.cols, size.rows), (100, 30));
+/// # std::io::Result::Ok(())
+/// ```
+pub fn events<R: Read + AsFd>(reader: R) -> Events<R> {
+    let (winch, pending) = match winch_pipe() {
+        Ok(fd) => (Some(fd), None),
+        Err(e) => (None, Some(e)),
+    };
+    Events {
+        input: RawInput::new(reader),
+        winch,
+        pending,
+        esc_timeout: ESC_TIMEOUT,
+    }
+}
+
+/// Iterator returned by [`events`].
+#[derive(Debug)]
+pub struct Events<R> {
+    input: RawInput<R>,
+    /// The read end of the `SIGWINCH` pipe, if the handler could be installed
+    winch: Option<BorrowedFd<'static>>,
+    /// An error from installing the handler, to be yielded first
+    pending: Option<io::Error>,
+    esc_timeout: Duration,
+}
+
+impl<R> Events<R> {
+    /// Set how long to wait for the rest of an escape sequence after an `ESC`
+    ///
+    /// Works like [`RawTerminal::set_esc_timeout`], and likewise defaults to 50 ms; pass
+    /// [`RawTerminal::esc_timeout`] to use the same timeout as the terminal.
+    pub fn set_esc_timeout(&mut self, timeout: Duration) {
+        self.esc_timeout = timeout;
+    }
+
+    /// Unwrap the inner reader, dropping any bytes read but not parsed yet.
+    pub fn into_inner(self) -> R {
+        self.input.into_inner()
+    }
+}
+
+impl<R: Read + AsFd> Iterator for Events<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.pending.take() {
+            return Some(Err(e));
+        }
+        if let Some(winch) = self.winch {
+            match self.wait(winch) {
+                Ok(false) => {}
+                Ok(true) => {
+                    let size = win_size(self.input.as_fd()).map(WinSize::from);
+                    return Some(size.map(Event::Resize));
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        match read_event(&mut self.input, self.esc_timeout) {
+            Ok((event, _)) => Some(Ok(event)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<R: AsFd> Events<R> {
+    /// Wait for input or a resize, returning `true` for a resize.
+    fn wait(&self, winch: BorrowedFd<'_>) -> io::Result<bool> {
+        if self.input.wait_readable(Some(Duration::ZERO))? {
+            return Ok(false);
+        }
+        let (_, resized) = wait_readable_either(self.input.as_fd(), winch)?;
+        if resized {
+            // Drain the pipe, so that a burst of signals is reported once
+            let mut buf = [0; 64];
+            while unsafe { libc::read(winch.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) } > 0 {
+            }
+        }
+        Ok(resized)
+    }
+}
+
+/// The write end of the `SIGWINCH` pipe, for the handler
+static WINCH_WRITE: AtomicI32 = AtomicI32::new(-1);
+/// The read end of the `SIGWINCH` pipe
+static WINCH_READ: OnceLock<OwnedFd> = OnceLock::new();
+
+extern "C" fn on_winch(_: libc::c_int) {
+    // A write to the full pipe fails, which is fine: a resize is already pending
+    preserving_errno(|| unsafe {
+        libc::write(
+            WINCH_WRITE.load(Ordering::Relaxed),
+            [0u8].as_ptr().cast(),
+            1,
+        );
+    });
+}
+
+/// Get the read end of the `SIGWINCH` pipe, installing the handler on the first call.
+fn winch_pipe() -> io::Result<BorrowedFd<'static>> {
+    static INSTALL: Mutex<()> = Mutex::new(());
+    let _guard = INSTALL.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(fd) = WINCH_READ.get() {
+        return Ok(fd.as_fd());
+    }
+    let (read, write) = pipe(true)?;
+    // Both ends exist before the handler is installed, so it always has somewhere to write
+    WINCH_WRITE.store(write.as_raw_fd(), Ordering::Relaxed);
+    if let Err(e) = set_handler(libc::SIGWINCH, on_winch) {
+        WINCH_WRITE.store(-1, Ordering::Relaxed);
+        return Err(e);
+    }
+    // The write end stays open for as long as the handler is installed, i.e. forever
+    let _ = write.into_raw_fd();
+    Ok(WINCH_READ.get_or_init(|| read).as_fd())
+}
@@ -0,0 +1,112 @@
+//! Raw mode combined with the alternate screen for full-screen applications.
+
+use std::{
+    io::{self, Write},
+    ops,
+    os::fd::AsFd,
+};
+
+use crate::sequences::{
+    ALT_SCREEN_ENTER, ALT_SCREEN_LEAVE, HIDE_CURSOR, MOUSE_SGR_OFF, MOUSE_SGR_ON, SHOW_CURSOR,
+};
+use crate::{IntoRawMode, RawTerminal};
+
+/// Optional modes enabled by [`FullscreenTerminal::enter_with`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FullscreenOptions {
+    /// Enable mouse reporting ([`MOUSE_SGR_ON`]).
+    pub mouse: bool,
+    /// Enable bracketed paste, as with [`RawTerminal::set_bracketed_paste`].
+    pub bracketed_paste: bool,
+}
+
+/// A terminal in raw mode, showing the alternate screen with the cursor hidden.
+///
+/// Setup happens in this order: raw mode, alternate screen, hidden cursor, then the optional
+/// modes. Each successful step registers its undo step with [`RawTerminal::defer`], so dropping
+/// undoes everything in exactly the reverse order, restoring the terminal attributes last. If
+/// setup fails halfway, the steps that already succeeded are undone the same way.
+///
+/// ```rust,no_run
+/// use termion_raw2::FullscreenTerminal;
+/// use std::io::{stdout, Write};
+///
+/// let mut term = FullscreenTerminal::enter(stdout())?;
+/// write!(term, "Hey there.")?;
+/// # std::io::Result::Ok(())
+/// ```
+pub struct FullscreenTerminal<W: Write + AsFd> {
+    term: RawTerminal<W>,
+}
+
+impl<W: Write + AsFd> FullscreenTerminal<W> {
+    /// Enter raw mode and switch to the alternate screen with a hidden cursor.
+    pub fn enter(writer: W) -> io::Result<Self> {
+        Self::enter_with(writer, FullscreenOptions::default())
+    }
+
+    /// Like [`enter`](Self::enter), also enabling the modes selected in `options`.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Read, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{FullscreenOptions, FullscreenTerminal};
+    ///
+    /// let options = FullscreenOptions { bracketed_paste: true, ..Default::default() };
+    /// let term = FullscreenTerminal::enter_with(tty, options)?;
+    /// drop(term);
+    /// # let expected = b"\x1b[?1049h\x1b[?25l\x1b[?2004h\x1b[?2004l\x1b[?25h\x1b[?1049l";
+    /// # let mut buf = vec![0; expected.len()];
+    /// # master.read_exact(&mut buf)?;
+    /// # assert_eq!(buf, expected);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn enter_with(writer: W, options: FullscreenOptions) -> io::Result<Self> {
+        let mut term = FullscreenTerminal {
+            term: writer.into_raw_mode()?,
+        };
+        term.step(ALT_SCREEN_ENTER, ALT_SCREEN_LEAVE)?;
+        term.step(HIDE_CURSOR, SHOW_CURSOR)?;
+        if options.mouse {
+            term.step(MOUSE_SGR_ON, MOUSE_SGR_OFF)?;
+        }
+        if options.bracketed_paste {
+            // Through the managed mode, so that `set_bracketed_paste` keeps track of it too
+            term.term.set_bracketed_paste(true)?;
+        }
+        Ok(term)
+    }
+
+    fn step(&mut self, setup: &'static str, undo: &'static str) -> io::Result<()> {
+        self.term.write_seq(setup)?;
+        self.term.defer(move |out| out.write_all(undo.as_bytes()));
+        Ok(())
+    }
+}
+
+impl<W: Write + AsFd> ops::Deref for FullscreenTerminal<W> {
+    type Target = RawTerminal<W>;
+
+    fn deref(&self) -> &RawTerminal<W> {
+        &self.term
+    }
+}
+
+impl<W: Write + AsFd> ops::DerefMut for FullscreenTerminal<W> {
+    fn deref_mut(&mut self) -> &mut RawTerminal<W> {
+        &mut self.term
+    }
+}
+
+impl<W: Write + AsFd> Write for FullscreenTerminal<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.term.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.term.flush()
+    }
+}
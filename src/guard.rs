@@ -0,0 +1,86 @@
+//! Restoring the terminal independently of the writer.
+
+use std::{
+    fmt,
+    io::{self, Write},
+    os::fd::{AsFd, AsRawFd, OwnedFd},
+};
+
+use crate::signal::SignalRestore;
+use crate::sys::{attr::set_terminal_attr, Termios};
+use crate::{RawTerminal, RestoreFn};
+
+/// Restores the terminal attributes when dropped.
+///
+/// Returned by [`RawTerminal::split`]. It owns its own file descriptor for the terminal, so it is
+/// unaffected by what happens to the writer. Keeping the guard alive is what keeps the terminal
+/// from being restored; the writer can be moved, wrapped or dropped freely.
+pub struct RestoreGuard {
+    fd: OwnedFd,
+    prev_ios: Termios,
+    restore_fn: Option<RestoreFn>,
+    _signal_restore: Option<SignalRestore>,
+}
+
+impl Drop for RestoreGuard {
+    fn drop(&mut self) {
+        let _result = match &mut self.restore_fn {
+            Some(restore) => restore(self.fd.as_fd(), &self.prev_ios),
+            None => set_terminal_attr(self.fd.as_fd(), &self.prev_ios),
+        };
+        #[cfg(feature = "log")]
+        log::debug!(
+            "restoring fd {} on drop: {:?}",
+            self.fd.as_raw_fd(),
+            _result
+        );
+    }
+}
+
+impl fmt::Debug for RestoreGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RestoreGuard")
+            .field("fd", &self.fd.as_raw_fd())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Write + AsFd> RawTerminal<W> {
+    /// Separate the writer from the guard restoring the terminal
+    ///
+    /// The terminal stays in raw mode until the returned [`RestoreGuard`] is dropped, regardless
+    /// of what happens to the writer. The guard uses the separate control fd if there is one, and
+    /// otherwise a duplicate of the writer's fd, which is why this can fail. Handlers installed
+    /// with [`install_signal_restore`](Self::install_signal_restore) move into the guard. Steps
+    /// registered with [`defer`](Self::defer) need the writer, so they are dropped without running.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// # let probe = tty.try_clone()?;
+    /// use termion_raw2::{is_raw, IntoRawMode};
+    ///
+    /// let (writer, guard) = tty.into_raw_mode()?.split()?;
+    /// drop(writer);
+    /// assert!(is_raw(&probe)?);
+    /// drop(guard);
+    /// assert!(!is_raw(&probe)?);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn split(mut self) -> io::Result<(W, RestoreGuard)> {
+        let fd = match self.control.take() {
+            Some(fd) => fd,
+            None => self.output.as_fd().try_clone_to_owned()?,
+        };
+        let guard = RestoreGuard {
+            fd,
+            prev_ios: *self.restore_target(),
+            restore_fn: self.restore_fn.take(),
+            _signal_restore: self.signal_restore.take(),
+        };
+        Ok((self.into_output(), guard))
+    }
+}
@@ -0,0 +1,241 @@
+//! Buffered raw mode input.
+
+use std::{
+    io::{self, BufRead, Read},
+    os::fd::{AsFd, BorrowedFd},
+    time::Duration,
+};
+
+use crate::sys::poll::wait_readable;
+
+/// A reader which bytes can be pushed back into.
+///
+/// Parsing keys sometimes means reading one byte too many, e.g. an `ESC` which turns out to
+/// start the next key rather than continue the current one. [`unget`](Self::unget) puts such a
+/// byte back, so the next read returns it again.
+///
+/// Pushed back bytes are read before anything else, the last one pushed back first. Reading
+/// only blocks on the inner reader when no pushed back bytes are left.
+///
+/// ```rust
+/// use termion_raw2::RawInput;
+///
+/// let mut input = RawInput::new(&b"bc"[..]);
+/// input.unget(b'a');
+/// assert_eq!(input.read_byte()?, Some(b'a'));
+/// assert_eq!(input.read_byte()?, Some(b'b'));
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct RawInput<R> {
+    reader: R,
+    /// Pushed back bytes, the next one to read last
+    pushed: Vec<u8>,
+}
+
+impl<R> RawInput<R> {
+    /// Wrap `reader`.
+    pub fn new(reader: R) -> Self {
+        RawInput {
+            reader,
+            pushed: Vec::new(),
+        }
+    }
+
+    /// Push `byte` back, so that it is returned by the next read.
+    pub fn unget(&mut self, byte: u8) {
+        self.pushed.push(byte);
+    }
+
+    /// Get a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the inner reader.
+    ///
+    /// Reading from it directly skips any pushed back bytes.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap the inner reader, dropping any pushed back bytes.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> RawInput<R> {
+    /// Read a single byte, returning `None` on end of input.
+    ///
+    /// Reads that are interrupted by a signal are retried.
+    pub fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        match self.pushed.pop() {
+            Some(byte) => Ok(Some(byte)),
+            None => read_byte(&mut self.reader),
+        }
+    }
+}
+
+impl<R: AsFd> RawInput<R> {
+    /// Wait until a byte can be read, returning `false` if `timeout` elapses first.
+    pub(crate) fn wait_readable(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        if !self.pushed.is_empty() {
+            return Ok(true);
+        }
+        wait_readable(self.reader.as_fd(), timeout)
+    }
+}
+
+/// Returns the pushed back bytes without touching the inner reader if there are any.
+impl<R: Read> Read for RawInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pushed.is_empty() {
+            return self.reader.read(buf);
+        }
+        let n = buf.len().min(self.pushed.len());
+        for byte in &mut buf[..n] {
+            *byte = self.pushed.pop().unwrap();
+        }
+        Ok(n)
+    }
+}
+
+impl<R: AsFd> AsFd for RawInput<R> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.reader.as_fd()
+    }
+}
+
+/// A buffered reader for raw mode input, implementing [`BufRead`].
+///
+/// Works like [`io::BufReader`], with the edge cases of raw mode input defined as follows:
+///
+/// - With a read timeout ([`ReadMode::Timeout`](crate::ReadMode::Timeout)) or in
+///   [`ReadMode::NonBlocking`](crate::ReadMode::NonBlocking), a read that finds no input returns
+///   0 bytes. [`fill_buf`](BufRead::fill_buf) then returns an empty buffer, which `BufRead`
+///   methods like [`read_line`](BufRead::read_line) treat as the end of input. Unlike a real end
+///   of input this isn't sticky: the next call reads again.
+/// - A [`WouldBlock`](io::ErrorKind::WouldBlock) error from a reader with `O_NONBLOCK` set is
+///   returned as is, with the buffer left empty.
+/// - Reads interrupted by a signal are retried.
+///
+/// ```rust
+/// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # assert_eq!(ret, 0);
+/// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// use termion_raw2::{RawBufReader, RawModeBuilder, ReadMode};
+/// use std::{io::BufRead, time::Duration};
+///
+/// let mut input = RawBufReader::new(tty.try_clone()?);
+/// let tty = RawModeBuilder::new()
+///     .read_mode(ReadMode::Timeout(Duration::from_millis(100)))
+///     .apply_batched(tty)?;
+/// # master.write_all(b"one\rtwo")?;
+/// let mut line = Vec::new();
+/// input.read_until(b'\r', &mut line)?;
+/// assert_eq!(line, b"one\r");
+/// // Without a terminating `\r`, reading stops at the timeout
+/// line.clear();
+/// input.read_until(b'\r', &mut line)?;
+/// assert_eq!(line, b"two");
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct RawBufReader<R> {
+    reader: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<R> RawBufReader<R> {
+    /// Wrap `reader` with a buffer of the default capacity.
+    pub fn new(reader: R) -> Self {
+        Self::with_capacity(1024, reader)
+    }
+
+    /// Wrap `reader` with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, reader: R) -> Self {
+        RawBufReader {
+            reader,
+            buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// The bytes read but not consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Get a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the inner reader.
+    ///
+    /// Reading from it directly skips the buffered bytes.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap the inner reader, dropping the buffered bytes.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Read for RawBufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for RawBufReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.filled {
+            let n = loop {
+                match self.reader.read(&mut self.buf) {
+                    Ok(n) => break n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            };
+            self.pos = 0;
+            self.filled = n;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+impl<R: AsFd> AsFd for RawBufReader<R> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.reader.as_fd()
+    }
+}
+
+/// Read a single byte, returning `None` on end of input.
+pub(crate) fn read_byte<R: Read>(reader: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
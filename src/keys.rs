@@ -0,0 +1,654 @@
+//! Reading keys from a terminal in raw mode.
+
+use std::{
+    io::{self, Read, Write},
+    ops,
+    os::fd::AsFd,
+    time::Duration,
+};
+
+use crate::input::read_byte;
+use crate::mouse::parse_mouse_prefix;
+use crate::{Event, RawInput, RawTerminal};
+
+/// How long to wait for the rest of an escape sequence before treating `ESC` as a key press,
+/// unless changed with [`RawTerminal::set_esc_timeout`].
+pub(crate) const ESC_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// A key press.
+///
+/// More keys may be added in the future, so matches need a wildcard arm. Until then, sequences
+/// that aren't recognized are reported as [`Key::Unknown`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Key {
+    /// Backspace.
+    Backspace,
+    /// Left arrow.
+    Left,
+    /// Right arrow.
+    Right,
+    /// Up arrow.
+    Up,
+    /// Down arrow.
+    Down,
+    /// Home key.
+    Home,
+    /// End key.
+    End,
+    /// Page Up key.
+    PageUp,
+    /// Page Down key.
+    PageDown,
+    /// Backward Tab key.
+    BackTab,
+    /// Delete key.
+    Delete,
+    /// Insert key.
+    Insert,
+    /// Function keys.
+    ///
+    /// Only function keys 1 through 12 are supported.
+    F(u8),
+    /// Normal character. Enter is reported as `Char('\n')` and Tab as `Char('\t')`.
+    Char(char),
+    /// Alt modified character.
+    Alt(char),
+    /// Ctrl modified character.
+    ///
+    /// Note that certain keys may not be modifiable with `ctrl`, due to limitations of terminals.
+    Ctrl(char),
+    /// Null byte.
+    Null,
+    /// Esc key.
+    Esc,
+    /// A key pressed with modifiers that the variants above can't express, e.g. Ctrl+Up or
+    /// Ctrl+Shift+A.
+    ///
+    /// Reported for xterm style sequences with a modifier parameter (`ESC [ 1 ; 5 A`) and for
+    /// the CSI-u sequences of the kitty keyboard protocol. Modifiers that have a variant of their
+    /// own are reported as such instead: a lone Alt on a character as [`Key::Alt`], a lone Ctrl
+    /// on a letter as [`Key::Ctrl`], a lone Shift on a letter as the uppercase [`Key::Char`] and
+    /// Shift+Tab as [`Key::BackTab`].
+    Modified(Box<Key>, Modifiers),
+    /// A sequence of bytes that couldn't be recognized as a key.
+    ///
+    /// The bytes are kept as read, so nothing is silently dropped.
+    Unknown(Vec<u8>),
+}
+
+impl Key {
+    /// The character typed, if this is a [`Key::Char`].
+    ///
+    /// ```rust
+    /// use termion_raw2::Key;
+    ///
+    /// assert_eq!(Key::Char('q').as_char(), Some('q'));
+    /// assert_eq!(Key::Alt('q').as_char(), None);
+    /// ```
+    pub fn as_char(&self) -> Option<char> {
+        match self {
+            Key::Char(c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a sequence that wasn't recognized.
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Key::Unknown(_))
+    }
+
+    /// Combine the key with `modifiers`, using the simplest variant that expresses both.
+    fn with_modifiers(self, modifiers: Modifiers) -> Key {
+        match (self, modifiers) {
+            (key, Modifiers(0)) => key,
+            (Key::Char(c), Modifiers::ALT) => Key::Alt(c),
+            (Key::Char(c), Modifiers::CTRL) if c.is_ascii_alphabetic() => {
+                Key::Ctrl(c.to_ascii_lowercase())
+            }
+            (Key::Char(c), Modifiers::SHIFT) if c.is_ascii_alphabetic() => {
+                Key::Char(c.to_ascii_uppercase())
+            }
+            (Key::Char('\t'), Modifiers::SHIFT) => Key::BackTab,
+            (key, modifiers) => Key::Modified(Box::new(key), modifiers),
+        }
+    }
+}
+
+/// The modifiers held down with a [`Key::Modified`].
+///
+/// The bits are those of the xterm and kitty encodings, where the modifier parameter of a
+/// sequence is one more than the bits.
+///
+/// ```rust
+/// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # assert_eq!(ret, 0);
+/// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// use termion_raw2::{IntoRawMode, Key, Modifiers, RawInput};
+///
+/// let mut input = RawInput::new(tty.try_clone()?);
+/// let mut tty = tty.into_raw_mode()?;
+/// # master.write_all(b"\x1b[1;5A\x1b[105;5u\t\x1b[97;6u")?;
+/// assert_eq!(tty.read_key(&mut input)?, Key::Modified(Box::new(Key::Up), Modifiers::CTRL));
+/// // With the kitty keyboard protocol, Ctrl+I and Tab are different keys
+/// assert_eq!(tty.read_key(&mut input)?, Key::Ctrl('i'));
+/// assert_eq!(tty.read_key(&mut input)?, Key::Char('\t'));
+/// assert_eq!(
+///     tty.read_key(&mut input)?,
+///     Key::Modified(Box::new(Key::Char('a')), Modifiers::CTRL | Modifiers::SHIFT)
+/// );
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    /// Shift key.
+    pub const SHIFT: Self = Self(1);
+    /// Alt (or Option) key.
+    pub const ALT: Self = Self(2);
+    /// Ctrl key.
+    pub const CTRL: Self = Self(4);
+    /// Super (Windows or Command) key. Only reported by the kitty keyboard protocol.
+    pub const SUPER: Self = Self(8);
+    /// Hyper key. Only reported by the kitty keyboard protocol.
+    pub const HYPER: Self = Self(16);
+    /// Meta key. Only reported by the kitty keyboard protocol.
+    pub const META: Self = Self(32);
+    /// Caps Lock is on. Only reported by the kitty keyboard protocol.
+    pub const CAPS_LOCK: Self = Self(64);
+    /// Num Lock is on. Only reported by the kitty keyboard protocol.
+    pub const NUM_LOCK: Self = Self(128);
+
+    /// No modifiers.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// The raw bits of the modifiers.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Create modifiers from raw bits.
+    pub const fn from_bits_retain(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    /// Whether all modifiers in `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl ops::BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl<W: Write + AsFd> RawTerminal<W> {
+    /// Block until a key is pressed and return it.
+    ///
+    /// Bytes are read one at a time, so multi-byte characters and escape sequences are
+    /// reassembled even if they arrive across several reads. A lone `ESC` is reported as
+    /// [`Key::Esc`] if no further bytes arrive within the timeout set with
+    /// [`set_esc_timeout`](Self::set_esc_timeout).
+    ///
+    /// The input is read through a [`RawInput`], so that an `ESC` arriving in the middle of an
+    /// escape sequence can be pushed back and start the next key. An `ESC` pressed twice in a row
+    /// is reported as two [`Key::Esc`].
+    ///
+    /// Mouse reports are returned as [`Key::Unknown`]; use [`read_event`](Self::read_event) to
+    /// have them parsed. Returns an [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) error if the
+    /// input is closed before any byte is read.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{IntoRawMode, Key, RawInput};
+    ///
+    /// let mut input = RawInput::new(tty.try_clone()?);
+    /// let mut tty = tty.into_raw_mode()?;
+    /// # master.write_all(b"\x1b[")?;
+    /// # master.write_all(b"Aq\x1b\x1b[B")?;
+    /// assert_eq!(tty.read_key(&mut input)?, Key::Up);
+    /// assert_eq!(tty.read_key(&mut input)?, Key::Char('q'));
+    /// assert_eq!(tty.read_key(&mut input)?, Key::Esc);
+    /// assert_eq!(tty.read_key(&mut input)?, Key::Down);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn read_key<R: Read + AsFd>(&mut self, input: &mut RawInput<R>) -> io::Result<Key> {
+        read_key(input, self.esc_timeout)
+    }
+
+    /// Set how long to wait for the rest of an escape sequence after an `ESC`
+    ///
+    /// If nothing follows an `ESC` within this time, it is read as [`Key::Esc`]. Too short, and
+    /// escape sequences arriving with delays, e.g. over a slow ssh connection, fall apart into
+    /// separate keys. Too long, and pressing Escape feels laggy. The default is 50 ms.
+    pub fn set_esc_timeout(&mut self, timeout: Duration) {
+        self.esc_timeout = timeout;
+    }
+
+    /// How long to wait for the rest of an escape sequence after an `ESC`
+    pub fn esc_timeout(&self) -> Duration {
+        self.esc_timeout
+    }
+
+    /// Read a single UTF-8 encoded character.
+    ///
+    /// Reads one to four bytes as needed, so characters are reassembled even if their bytes
+    /// arrive across several reads. Returns `None` if the read returns no data, which happens on
+    /// end of input or when a read timeout is configured (see
+    /// [`ReadMode::Timeout`](crate::ReadMode::Timeout)). Fails with
+    /// [`InvalidData`](io::ErrorKind::InvalidData) if the bytes aren't valid UTF-8, or the input
+    /// ends in the middle of a character.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// let mut input = tty.try_clone()?;
+    /// let mut tty = tty.into_raw_mode()?;
+    /// # master.write_all(b"\xe2\x82")?;
+    /// # master.write_all(b"\xac")?;
+    /// assert_eq!(tty.read_char(&mut input)?, Some('€'));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn read_char<R: Read>(&mut self, reader: &mut R) -> io::Result<Option<char>> {
+        let Some(first) = read_byte(reader)? else {
+            return Ok(None);
+        };
+        let mut buf = vec![first];
+        loop {
+            match parse_char(&buf) {
+                Some(Some(c)) => return Ok(Some(c)),
+                Some(None) => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8"));
+                }
+                None => match read_byte(reader)? {
+                    Some(byte) => buf.push(byte),
+                    None => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "input ended in the middle of a UTF-8 character",
+                        ));
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Read one key; see [`RawTerminal::read_key`].
+fn read_key<R: Read + AsFd>(input: &mut RawInput<R>, esc_timeout: Duration) -> io::Result<Key> {
+    let (event, buf) = read_event(input, esc_timeout)?;
+    Ok(match event {
+        Event::Key(key) => key,
+        _ => Key::Unknown(buf),
+    })
+}
+
+/// Read one event, returning it together with the bytes it was parsed from.
+pub(crate) fn read_event<R: Read + AsFd>(
+    input: &mut RawInput<R>,
+    esc_timeout: Duration,
+) -> io::Result<(Event, Vec<u8>)> {
+    let mut buf = Vec::new();
+    loop {
+        // Only bytes following an ESC are subject to the timeout; the rest of a UTF-8
+        // character is always expected to arrive.
+        if buf.first() == Some(&0x1b) && !input.wait_readable(Some(esc_timeout))? {
+            return Ok((Event::Key(incomplete_key(&buf)), buf));
+        }
+        match input.read_byte()? {
+            // Another ESC can't continue an escape sequence, so it starts the next key
+            Some(0x1b) if buf.first() == Some(&0x1b) => {
+                input.unget(0x1b);
+                return Ok((Event::Key(incomplete_key(&buf)), buf));
+            }
+            Some(byte) => buf.push(byte),
+            None if buf.is_empty() => return Err(io::ErrorKind::UnexpectedEof.into()),
+            None => return Ok((Event::Key(incomplete_key(&buf)), buf)),
+        }
+        if let Some(event) = parse_event(&buf) {
+            if let Event::Paste(_) = event {
+                return read_paste(input, buf);
+            }
+            return Ok((event, buf));
+        }
+    }
+}
+
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Read pasted text up to the end marker, after `buf` has been parsed as the start marker.
+///
+/// The text is read without a timeout, so nothing inside it is mistaken for keys. If the input
+/// ends before the end marker, what arrived so far is returned.
+fn read_paste<R: Read>(input: &mut RawInput<R>, mut buf: Vec<u8>) -> io::Result<(Event, Vec<u8>)> {
+    let end = loop {
+        match input.read_byte()? {
+            Some(byte) => buf.push(byte),
+            None => break buf.len(),
+        }
+        if buf.ends_with(PASTE_END) {
+            break buf.len() - PASTE_END.len();
+        }
+    };
+    let text = String::from_utf8_lossy(&buf[PASTE_START.len()..end]).into_owned();
+    Ok((Event::Paste(text), buf))
+}
+
+/// Read keys from `reader`, grouping repeats of the same key that arrive in quick succession.
+///
+/// Each item is a key together with the number of times it was pressed in a row, where every
+/// repeat arrived within `window` of the one before. This turns e.g. a burst of arrow keys from
+/// a held-down key or a scroll wheel into a single `(Key::Down, 5)`. Different keys are never
+/// grouped together, even if they arrive within the window.
+///
+/// The iterator ends when the input is closed. An error is yielded as an item; if it happens
+/// while looking for repeats, the key read so far is yielded first.
+///
+/// ```rust
+/// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # assert_eq!(ret, 0);
+/// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// use termion_raw2::{coalesced_keys, IntoRawMode, Key};
+/// use std::time::Duration;
+///
+/// let input = tty.try_clone()?;
+/// let tty = tty.into_raw_mode()?;
+/// # master.write_all(b"\x1b[B\x1b[B\x1b[Bq")?;
+/// let mut keys = coalesced_keys(input, Duration::from_millis(20));
+/// assert_eq!(keys.next().unwrap()?, (Key::Down, 3));
+/// assert_eq!(keys.next().unwrap()?, (Key::Char('q'), 1));
+/// # std::io::Result::Ok(())
+/// ```
+pub fn coalesced_keys<R: Read + AsFd>(reader: R, window: Duration) -> CoalescedKeys<R> {
+    CoalescedKeys {
+        input: RawInput::new(reader),
+        window,
+        pending: None,
+        esc_timeout: ESC_TIMEOUT,
+    }
+}
+
+/// Iterator returned by [`coalesced_keys`].
+#[derive(Debug)]
+pub struct CoalescedKeys<R> {
+    input: RawInput<R>,
+    window: Duration,
+    /// A key (or error) read while looking for repeats, to be yielded next
+    pending: Option<io::Result<Key>>,
+    esc_timeout: Duration,
+}
+
+impl<R> CoalescedKeys<R> {
+    /// Set how long to wait for the rest of an escape sequence after an `ESC`
+    ///
+    /// Works like [`RawTerminal::set_esc_timeout`], and likewise defaults to 50 ms; pass
+    /// [`RawTerminal::esc_timeout`] to use the same timeout as the terminal.
+    pub fn set_esc_timeout(&mut self, timeout: Duration) {
+        self.esc_timeout = timeout;
+    }
+}
+
+impl<R: Read + AsFd> Iterator for CoalescedKeys<R> {
+    type Item = io::Result<(Key, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = match self
+            .pending
+            .take()
+            .unwrap_or_else(|| read_key(&mut self.input, self.esc_timeout))
+        {
+            Ok(key) => key,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return None,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let mut count = 1;
+        loop {
+            match self.input.wait_readable(Some(self.window)) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => {
+                    self.pending = Some(Err(e));
+                    break;
+                }
+            }
+            match read_key(&mut self.input, self.esc_timeout) {
+                Ok(key) if key == first => count += 1,
+                next => {
+                    self.pending = Some(next);
+                    break;
+                }
+            }
+        }
+        Some(Ok((first, count)))
+    }
+}
+
+/// The key for input that ended before `parse_event` could recognize it.
+fn incomplete_key(buf: &[u8]) -> Key {
+    if buf == [0x1b] {
+        Key::Esc
+    } else {
+        Key::Unknown(buf.to_vec())
+    }
+}
+
+/// Parse a complete event from `buf`, or return `None` if more bytes are needed.
+///
+/// When an event is returned, it always covers the whole buffer. The start of a bracketed paste
+/// is returned as an empty [`Event::Paste`], for `read_event` to read the rest.
+fn parse_event(buf: &[u8]) -> Option<Event> {
+    match buf {
+        b"\x1b[I" => return Some(Event::FocusGained),
+        b"\x1b[O" => return Some(Event::FocusLost),
+        _ if buf == PASTE_START => return Some(Event::Paste(String::new())),
+        _ => {}
+    }
+    if buf.starts_with(b"\x1b[<") || buf.starts_with(b"\x1b[M") {
+        return parse_mouse_prefix(buf).map(|parsed| match parsed {
+            Some((event, _)) => Event::Mouse(event),
+            None => Event::Key(Key::Unknown(buf.to_vec())),
+        });
+    }
+    parse_key(buf).map(Event::Key)
+}
+
+/// Parse a complete key from `buf`, or return `None` if more bytes are needed.
+///
+/// When a key is returned, it always covers the whole buffer.
+fn parse_key(buf: &[u8]) -> Option<Key> {
+    match buf {
+        [] => None,
+        [0x1b] => None,
+        [0x1b, b'O'] => None,
+        [0x1b, b'O', c] => Some(match c {
+            b'P'..=b'S' => Key::F(1 + c - b'P'),
+            b'A' => Key::Up,
+            b'B' => Key::Down,
+            b'C' => Key::Right,
+            b'D' => Key::Left,
+            b'H' => Key::Home,
+            b'F' => Key::End,
+            _ => Key::Unknown(buf.to_vec()),
+        }),
+        [0x1b, b'[', rest @ ..] => {
+            parse_csi(rest).map(|key| key.unwrap_or_else(|| Key::Unknown(buf.to_vec())))
+        }
+        [0x1b, rest @ ..] => match parse_char(rest)? {
+            Some(c) => Some(Key::Alt(c)),
+            None => Some(Key::Unknown(buf.to_vec())),
+        },
+        [b'\n' | b'\r'] => Some(Key::Char('\n')),
+        [b'\t'] => Some(Key::Char('\t')),
+        [0x7f] => Some(Key::Backspace),
+        [0x00] => Some(Key::Null),
+        [c @ 0x01..=0x1a] => Some(Key::Ctrl((c - 0x01 + b'a') as char)),
+        [c @ 0x1c..=0x1f] => Some(Key::Ctrl((c - 0x1c + b'4') as char)),
+        _ => match parse_char(buf)? {
+            Some(c) => Some(Key::Char(c)),
+            None => Some(Key::Unknown(buf.to_vec())),
+        },
+    }
+}
+
+/// Parse the part of a CSI sequence after `ESC [`.
+///
+/// Returns `None` if the sequence is incomplete, and `Some(None)` if it is complete but not
+/// recognized.
+fn parse_csi(buf: &[u8]) -> Option<Option<Key>> {
+    // The Linux console reports F1-F5 as `ESC [ [ A` through `ESC [ [ E`.
+    if let [b'[', rest @ ..] = buf {
+        return match rest {
+            [] => None,
+            [c @ b'A'..=b'E'] => Some(Some(Key::F(1 + c - b'A'))),
+            _ => Some(None),
+        };
+    }
+
+    let (&last, body) = buf.split_last()?;
+    match last {
+        // Parameter and intermediate bytes
+        0x20..=0x3f => return None,
+        // Final byte
+        0x40..=0x7e => {}
+        _ => return Some(None),
+    }
+    if body.iter().any(|b| !(0x20..=0x3f).contains(b)) {
+        return Some(None);
+    }
+
+    if last == b'u' {
+        return Some(parse_csi_u(body));
+    }
+
+    // xterm style modifiers follow the key's own parameter, e.g. `ESC [ 1 ; 5 A` for Ctrl+Up
+    let (body, modifiers) = match body.iter().position(|&b| b == b';') {
+        Some(i) => match parse_modifiers(&body[i + 1..]) {
+            Some(modifiers) => (&body[..i], modifiers),
+            None => return Some(None),
+        },
+        None => (body, Modifiers::empty()),
+    };
+
+    let key = match (body, last) {
+        (b"" | b"1", b'A') => Key::Up,
+        (b"" | b"1", b'B') => Key::Down,
+        (b"" | b"1", b'C') => Key::Right,
+        (b"" | b"1", b'D') => Key::Left,
+        (b"" | b"1", b'H') => Key::Home,
+        (b"" | b"1", b'F') => Key::End,
+        (b"", b'Z') => Key::BackTab,
+        // F1, F2 and F4 as sent by the kitty keyboard protocol; `ESC [ 1 ; m R` for F3 is left
+        // out, as it can't be told apart from a cursor position report
+        (b"" | b"1", b'P') => Key::F(1),
+        (b"" | b"1", b'Q') => Key::F(2),
+        (b"" | b"1", b'S') => Key::F(4),
+        (b"1" | b"7", b'~') => Key::Home,
+        (b"2", b'~') => Key::Insert,
+        (b"3", b'~') => Key::Delete,
+        (b"4" | b"8", b'~') => Key::End,
+        (b"5", b'~') => Key::PageUp,
+        (b"6", b'~') => Key::PageDown,
+        (b"11", b'~') => Key::F(1),
+        (b"12", b'~') => Key::F(2),
+        (b"13", b'~') => Key::F(3),
+        (b"14", b'~') => Key::F(4),
+        (b"15", b'~') => Key::F(5),
+        (b"17", b'~') => Key::F(6),
+        (b"18", b'~') => Key::F(7),
+        (b"19", b'~') => Key::F(8),
+        (b"20", b'~') => Key::F(9),
+        (b"21", b'~') => Key::F(10),
+        (b"23", b'~') => Key::F(11),
+        (b"24", b'~') => Key::F(12),
+        _ => return Some(None),
+    };
+    Some(Some(key.with_modifiers(modifiers)))
+}
+
+/// Parse the body of a CSI-u sequence, `code[:alternates][;modifiers[:event][;text]]`.
+///
+/// Key releases are reported by the kitty keyboard protocol with `REPORT_EVENTS`, but [`Key`]
+/// only describes presses, so they aren't recognized.
+fn parse_csi_u(body: &[u8]) -> Option<Key> {
+    let mut fields = body.split(|&b| b == b';');
+    let code = fields.next()?.split(|&b| b == b':').next()?;
+    let modifiers = match fields.next() {
+        Some(field) => parse_modifiers(field)?,
+        None => Modifiers::empty(),
+    };
+    let key = match parse_number(code)? {
+        9 => Key::Char('\t'),
+        13 => Key::Char('\n'),
+        27 => Key::Esc,
+        127 => Key::Backspace,
+        // The private use area holds the kitty codes of keys without a character, like the
+        // keypad or media keys
+        0xe000..=0xf8ff => return None,
+        code => Key::Char(char::from_u32(code)?),
+    };
+    Some(key.with_modifiers(modifiers))
+}
+
+/// Parse a modifier parameter with an optional event type, `modifiers[:event]`.
+///
+/// Returns `None` for a key release (event type 3) and for anything malformed.
+fn parse_modifiers(field: &[u8]) -> Option<Modifiers> {
+    let mut parts = field.split(|&b| b == b':');
+    let value = parse_number(parts.next()?)?;
+    match parts.next().map(parse_number) {
+        // Press or repeat
+        None | Some(Some(1 | 2)) => {}
+        _ => return None,
+    }
+    let bits = u8::try_from(value.checked_sub(1)?).ok()?;
+    Some(Modifiers(bits))
+}
+
+/// Parse a decimal parameter of a CSI sequence.
+fn parse_number(digits: &[u8]) -> Option<u32> {
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+/// Decode a single UTF-8 character from `buf`.
+///
+/// Returns `None` if more bytes are needed, and `Some(None)` if `buf` is not a valid encoding
+/// of exactly one character.
+fn parse_char(buf: &[u8]) -> Option<Option<char>> {
+    match std::str::from_utf8(buf) {
+        Ok(s) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(Some(c)),
+                _ => Some(None),
+            }
+        }
+        // The buffer is a valid prefix of a character that isn't complete yet
+        Err(e) if e.valid_up_to() == 0 && e.error_len().is_none() => None,
+        Err(_) => Some(None),
+    }
+}
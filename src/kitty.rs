@@ -0,0 +1,256 @@
+//! Toggling the kitty keyboard protocol.
+//!
+//! Terminals supporting the [kitty keyboard protocol] keep a stack of keyboard flags. Pushing
+//! flags makes the terminal report keys as CSI-u sequences (`ESC [ code ; modifiers u`), which
+//! tell apart combinations the legacy encoding can't, such as Ctrl+I and Tab.
+//! [`read_key`](RawTerminal::read_key) decodes them into [`Key`](crate::Key) values with
+//! [`Modifiers`](crate::Modifiers).
+//!
+//! [kitty keyboard protocol]: https://sw.kovidgoyal.net/kitty/keyboard-protocol/
+
+#[cfg(feature = "input")]
+use std::{
+    io::Read,
+    time::{Duration, Instant},
+};
+use std::{
+    io::{self, Write},
+    ops,
+    os::fd::AsFd,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc,
+    },
+};
+
+#[cfg(feature = "input")]
+use crate::escape::{next_byte, read_escape_sequence, ESC};
+#[cfg(feature = "input")]
+use crate::RawInput;
+use crate::{sequences, RawTerminal, KITTY_KEYBOARD};
+
+/// Keyboard flags of the kitty keyboard protocol, pushed with
+/// [`RawTerminal::push_kitty_keyboard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KittyKeyboardFlags(u8);
+
+impl KittyKeyboardFlags {
+    /// Report keys that are ambiguous in the legacy encoding, like Esc, Alt+key and Ctrl+key,
+    /// as CSI-u sequences.
+    pub const DISAMBIGUATE: Self = Self(1);
+    /// Report repeats and releases as well as presses.
+    pub const REPORT_EVENTS: Self = Self(2);
+    /// Report the shifted and base layout keys too.
+    pub const REPORT_ALTERNATES: Self = Self(4);
+    /// Report all keys as CSI-u sequences, including Enter, Tab, Backspace and plain text.
+    pub const REPORT_ALL_AS_ESCAPES: Self = Self(8);
+    /// Report the text a key produces along with the key.
+    pub const REPORT_TEXT: Self = Self(16);
+
+    /// No flags set, i.e. the legacy encoding.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// The raw bits of the flags.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Create flags from raw bits, keeping bits that have no named constant.
+    pub const fn from_bits_retain(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    /// Whether all flags in `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl ops::BitOr for KittyKeyboardFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for KittyKeyboardFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl<W: Write + AsFd> RawTerminal<W> {
+    /// Push `flags` onto the terminal's stack of keyboard flags
+    ///
+    /// Writes `ESC [ > flags u`. Terminals that don't support the kitty keyboard protocol
+    /// ignore it and keep sending legacy sequences, which [`read_key`](Self::read_key) decodes
+    /// as before; use [`kitty_keyboard_flags`](Self::kitty_keyboard_flags) to find out whether
+    /// the terminal understood. Every level still pushed is popped on drop, before the
+    /// attributes are restored.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Read, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{IntoRawMode, KittyKeyboardFlags};
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// tty.push_kitty_keyboard(KittyKeyboardFlags::DISAMBIGUATE)?;
+    /// tty.push_kitty_keyboard(KittyKeyboardFlags::DISAMBIGUATE | KittyKeyboardFlags::REPORT_EVENTS)?;
+    /// tty.pop_kitty_keyboard()?;
+    /// drop(tty);
+    /// # let mut buf = [0; 19];
+    /// # master.read_exact(&mut buf)?;
+    /// # assert_eq!(&buf, b"\x1b[>1u\x1b[>3u\x1b[<u\x1b[<1u");
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn push_kitty_keyboard(&mut self, flags: KittyKeyboardFlags) -> io::Result<()> {
+        self.write_seq(&format!("\x1b[>{}u", flags.bits()))?;
+        self.kitty_pushes.fetch_add(1, Ordering::Relaxed);
+        if self.reset_modes & KITTY_KEYBOARD == 0 {
+            self.reset_modes |= KITTY_KEYBOARD;
+            let pushes = Arc::clone(&self.kitty_pushes);
+            self.defer(move |out| pop_all(out, &pushes));
+        }
+        Ok(())
+    }
+
+    /// Pop the flags pushed last with [`push_kitty_keyboard`](Self::push_kitty_keyboard)
+    ///
+    /// Writes [`KITTY_KEYBOARD_POP`](sequences::KITTY_KEYBOARD_POP). Does nothing if every push
+    /// has already been popped, so flags pushed by someone else are left alone.
+    pub fn pop_kitty_keyboard(&mut self) -> io::Result<()> {
+        let popped = self
+            .kitty_pushes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        if popped.is_ok() {
+            self.write_seq(sequences::KITTY_KEYBOARD_POP)?;
+        }
+        Ok(())
+    }
+
+    /// Ask the terminal for its current keyboard flags
+    ///
+    /// Writes [`KITTY_KEYBOARD_QUERY`](sequences::KITTY_KEYBOARD_QUERY) followed by
+    /// [`DEVICE_ATTRIBUTES_QUERY`](sequences::DEVICE_ATTRIBUTES_QUERY), and reads replies from
+    /// `input` until the answer to the latter arrives. Returns `None` if the terminal doesn't
+    /// support the kitty keyboard protocol: it then only answers the second query, or nothing
+    /// at all within `timeout`.
+    ///
+    /// Other bytes arriving in the meantime, e.g. keys typed during the query, are pushed back
+    /// to `input` to be read afterwards, though escape sequences among them are dropped.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{IntoRawMode, KittyKeyboardFlags, RawInput};
+    /// use std::time::Duration;
+    ///
+    /// let mut input = RawInput::new(tty.try_clone()?);
+    /// let mut tty = tty.into_raw_mode()?;
+    /// let timeout = Duration::from_millis(100);
+    /// # master.write_all(b"\x1b[?1u\x1b[?62;22c\x1b[?62;22c")?;
+    /// let flags = tty.kitty_keyboard_flags(&mut input, timeout)?;
+    /// assert_eq!(flags, Some(KittyKeyboardFlags::DISAMBIGUATE));
+    /// // A terminal without support only answers the device attributes query
+    /// assert_eq!(tty.kitty_keyboard_flags(&mut input, timeout)?, None);
+    ///
+    /// // Keys typed while waiting for the replies are kept
+    /// # master.write_all(b"q\x1b[?0u\x1b[?62;22c")?;
+    /// assert_eq!(tty.kitty_keyboard_flags(&mut input, timeout)?, Some(KittyKeyboardFlags::empty()));
+    /// assert_eq!(input.read_byte()?, Some(b'q'));
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[cfg(feature = "input")]
+    pub fn kitty_keyboard_flags<R: Read + AsFd>(
+        &mut self,
+        input: &mut RawInput<R>,
+        timeout: Duration,
+    ) -> io::Result<Option<KittyKeyboardFlags>> {
+        self.write_seq(sequences::KITTY_KEYBOARD_QUERY)?;
+        self.write_seq(sequences::DEVICE_ATTRIBUTES_QUERY)?;
+
+        let mut typed = Vec::new();
+        let flags = read_replies(input, Instant::now() + timeout, &mut typed);
+        for &byte in typed.iter().rev() {
+            input.unget(byte);
+        }
+        flags
+    }
+
+    /// Push `flags` if the terminal supports the kitty keyboard protocol
+    ///
+    /// Asks with [`kitty_keyboard_flags`](Self::kitty_keyboard_flags) first, and only pushes
+    /// the flags with [`push_kitty_keyboard`](Self::push_kitty_keyboard) if an answer came.
+    /// Returns whether they were pushed; if not, keys keep arriving in the legacy encoding.
+    #[cfg(feature = "input")]
+    pub fn try_push_kitty_keyboard<R: Read + AsFd>(
+        &mut self,
+        input: &mut RawInput<R>,
+        flags: KittyKeyboardFlags,
+        timeout: Duration,
+    ) -> io::Result<bool> {
+        if self.kitty_keyboard_flags(input, timeout)?.is_none() {
+            return Ok(false);
+        }
+        self.push_kitty_keyboard(flags)?;
+        Ok(true)
+    }
+}
+
+/// Pop every level still pushed, as the deferred step of `push_kitty_keyboard`
+fn pop_all<W: Write>(out: &mut W, pushes: &AtomicU16) -> io::Result<()> {
+    match pushes.swap(0, Ordering::Relaxed) {
+        0 => Ok(()),
+        n => write!(out, "\x1b[<{n}u"),
+    }
+}
+
+/// Read the replies to `kitty_keyboard_flags`'s queries, up to the device attributes or until
+/// `deadline` passes, collecting other bytes in `typed`.
+#[cfg(feature = "input")]
+fn read_replies<R: Read + AsFd>(
+    input: &mut RawInput<R>,
+    deadline: Instant,
+    typed: &mut Vec<u8>,
+) -> io::Result<Option<KittyKeyboardFlags>> {
+    let mut flags = None;
+    loop {
+        match next_byte(input, deadline)? {
+            None => return Ok(flags),
+            Some(ESC) => input.unget(ESC),
+            Some(byte) => {
+                typed.push(byte);
+                continue;
+            }
+        }
+        let seq = match read_escape_sequence(input, deadline) {
+            Ok(Some(seq)) => seq,
+            Ok(None) => return Ok(flags),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(flags),
+            Err(e) => return Err(e),
+        };
+        if let Some(body) = seq.strip_prefix(b"\x1b[?") {
+            match body.split_last() {
+                Some((b'u', bits)) => flags = parse_flags(bits),
+                Some((b'c', _)) => return Ok(flags),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parse the flags in a reply to `KITTY_KEYBOARD_QUERY`
+#[cfg(feature = "input")]
+fn parse_flags(bits: &[u8]) -> Option<KittyKeyboardFlags> {
+    let bits = std::str::from_utf8(bits).ok()?.parse().ok()?;
+    Some(KittyKeyboardFlags::from_bits_retain(bits))
+}
@@ -23,30 +23,303 @@
 //! write!(stdout, "Hey there.").unwrap();
 //! # std::io::Result::Ok(())
 //! ```
+//!
+//! ### Features
+//!
+//! - `input` (default): reading keys from a terminal in raw mode.
+//! - `sequences` (default): escape sequence constants and full-screen terminals.
+//! - `log`: log every read and write of the terminal attributes, and the restore on drop, with
+//!   [`log::debug!`](https://docs.rs/log).
+//! - `nix-backend`: read and write the terminal attributes through [`nix`](https://docs.rs/nix)
+//!   instead of calling `libc` directly.
+//!
+//! Without default features, key input, escape sequences and full-screen terminals are left out.
+//! What remains is everything around the terminal attributes: `RawTerminal` with `IntoRawMode`
+//! and `RawModeBuilder`, `RestoreGuard` and the restore registry, restoring on signals, baud rates
+//! and window sizes, `raw_stdin` and `raw_stdout`, `SyncRawTerminal`, the output writers like
+//! `CrlfWriter`, and `termion_compat`.
+#![cfg_attr(
+    not(feature = "input"),
+    doc = "\n```compile_fail\n// Left out without `input`\nuse termion_raw2::{Key, RawInput};\n```"
+)]
+#![cfg_attr(
+    not(feature = "sequences"),
+    doc = "\n```compile_fail\n// Left out without `sequences`\nuse termion_raw2::{sequences, FullscreenTerminal, TrackingWriter};\n```"
+)]
+//!
+//! ### Miri
+//!
+//! Under Miri, which can't call into `libc`, reading and setting the terminal attributes is
+//! simulated in memory. This lets the lifecycle of a `RawTerminal` (entering raw mode, writing,
+//! suspending, activating and restoring) run under Miri, e.g. with `cargo miri test`. Other
+//! operations still need a real terminal.
 
 use std::{
+    cell::Cell,
+    fmt,
     io::{self, Write},
+    mem::{self, ManuallyDrop},
+    num::NonZeroU32,
     ops,
-    os::fd::AsFd,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
+    path::PathBuf,
+    process,
+    time::Duration,
 };
 
-use sys::attr::{get_terminal_attr, raw_terminal_attr, set_terminal_attr};
-use sys::Termios;
+#[cfg(feature = "sequences")]
+use std::sync::{atomic::AtomicU16, Arc};
 
+use signal::SignalRestore;
+use sys::attr::{
+    attrs_equal, get_terminal_attr, raw_attrs_match, raw_terminal_attr, set_terminal_attr,
+    set_terminal_attr_if_changed, set_terminal_attr_with,
+};
+use sys::poll::{wait_readable, wait_writable};
+use sys::tty::{bytes_available, device, foreground_pgrp, is_tty, tty_name};
+
+pub use baud::BaudRate;
+pub use builder::{RawConfig, RawModeBuilder, ReadMode};
+pub use error::RawModeError;
+#[cfg(feature = "input")]
+pub use event::{events, Event, Events};
+#[cfg(feature = "sequences")]
+pub use fullscreen::{FullscreenOptions, FullscreenTerminal};
+pub use guard::RestoreGuard;
+#[cfg(feature = "input")]
+pub use input::{RawBufReader, RawInput};
+#[cfg(feature = "input")]
+pub use keys::{coalesced_keys, CoalescedKeys, Key, Modifiers};
+#[cfg(feature = "sequences")]
+pub use kitty::KittyKeyboardFlags;
+#[cfg(feature = "input")]
+pub use mouse::{parse_mouse, MouseButton, MouseEvent, MouseEventKind};
+pub use registry::{register_restore, restore_all, restore_all_at_exit};
+#[cfg(feature = "input")]
+pub use script::{RecordingReader, Script, ScriptedReader};
+pub use signal::Signal;
+pub use size::WinSize;
+pub use stdio::{raw_stdin, raw_stdout, RawStdin, RawStdout};
+pub use sync::SyncRawTerminal;
+pub use sys::attr::SetMode;
+pub use termios::{
+    CharSize, ControlChars, ControlFlags, InputFlags, LocalFlags, OutputFlags, TermiosSnapshot,
+};
+pub use token::RawToken;
+#[cfg(feature = "sequences")]
+pub use writers::TrackingWriter;
+pub use writers::{AutoFlush, CrlfWriter, LineFlushWriter, ThrottledWriter};
+
+/// The terminal attributes, as defined by `libc`.
+pub use sys::Termios;
+
+mod baud;
+mod builder;
+mod error;
+#[cfg(feature = "input")]
+mod escape;
+#[cfg(feature = "input")]
+mod event;
+#[cfg(feature = "sequences")]
+mod fullscreen;
+mod guard;
+#[cfg(feature = "input")]
+mod input;
+#[cfg(feature = "input")]
+mod keys;
+#[cfg(feature = "sequences")]
+mod kitty;
+#[cfg(feature = "input")]
+mod mouse;
+mod registry;
+#[cfg(feature = "input")]
+mod script;
+#[cfg(feature = "sequences")]
+pub mod sequences;
+mod signal;
+mod size;
+mod stdio;
+mod sync;
 mod sys;
+pub mod termion_compat;
+mod termios;
+mod token;
+mod writers;
 
 /// A terminal restorer, which keeps the previous state of the terminal, and restores it, when
 /// dropped.
 ///
 /// Restoring will entirely bring back the old TTY state.
+///
+/// By default the terminal attributes are controlled through the output's own file descriptor.
+/// A separate control fd can be given with [`IntoRawMode::into_raw_mode_with_control_fd`], in
+/// which case the output is only used for writing.
+///
+/// # Threads
+///
+/// A `RawTerminal` is [`Send`] if the writer is, but not [`Sync`]. The terminal is restored on
+/// whichever thread drops it, at that point, regardless of what other threads do with the same
+/// terminal through other handles, e.g. reading from stdin. To share the terminal itself between
+/// threads, wrap it in a [`SyncRawTerminal`], which serializes writes and mode switches.
+///
+/// # File descriptors that change
+///
+/// The output's fd is never cached: it is asked for with [`AsFd::as_fd`] on every operation,
+/// including the restore on drop. A writer that reopens its underlying file therefore gets the
+/// old attributes restored on whatever fd it reports at that point. The one exception is
+/// [`install_signal_restore`](Self::install_signal_restore), whose signal handler can only use the
+/// fd from the time it was installed.
+///
+/// What is cached are the raw mode attributes applied by
+/// [`activate_raw_mode`](Self::activate_raw_mode), which assumes the fd keeps referring to the
+/// same terminal. For writers that may switch terminals, turn this off with
+/// [`set_attr_caching`](Self::set_attr_caching).
+///
+/// ```rust
+/// # use std::{fs::File, os::fd::FromRawFd, ptr};
+/// # fn pty() -> File {
+/// #     let (mut master, mut slave) = (0, 0);
+/// #     let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// #     assert_eq!(ret, 0);
+/// #     unsafe { File::from_raw_fd(slave) }
+/// # }
+/// use termion_raw2::{is_raw, IntoRawMode};
+/// use std::{io::{self, Write}, os::fd::{AsFd, BorrowedFd}};
+///
+/// struct Reopening {
+///     files: [File; 2],
+///     current: usize,
+/// }
+///
+/// impl Write for Reopening {
+///     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+///         self.files[self.current].write(buf)
+///     }
+///
+///     fn flush(&mut self) -> io::Result<()> {
+///         self.files[self.current].flush()
+///     }
+/// }
+///
+/// impl AsFd for Reopening {
+///     fn as_fd(&self) -> BorrowedFd<'_> {
+///         self.files[self.current].as_fd()
+///     }
+/// }
+///
+/// let mut term = Reopening { files: [pty(), pty()], current: 0 }.into_raw_mode()?;
+/// term.set_attr_caching(false);
+/// term.current = 1;
+/// term.activate_raw_mode()?;
+/// assert!(is_raw(&term.files[1])?);
+///
+/// let second = term.files[1].try_clone()?;
+/// drop(term);
+/// // The restore went to the fd reported at drop time
+/// assert!(!is_raw(&second)?);
+/// # std::io::Result::Ok(())
+/// ```
 pub struct RawTerminal<W: Write + AsFd> {
     prev_ios: Termios,
-    output: W,
+    /// Restored in place of `prev_ios` on drop, set by `into_raw_mode_restoring_to`
+    restore_ios: Option<Termios>,
+    /// The raw mode attributes, applied again by `activate_raw_mode`
+    raw_ios: Termios,
+    cache_attrs: bool,
+    /// Dropped in `Drop`, unless `into_output` took it out
+    output: ManuallyDrop<W>,
+    output_taken: bool,
+    control: Option<OwnedFd>,
+    signal_restore: Option<SignalRestore>,
+    deferred: Vec<Teardown<W>>,
+    state: Cell<RawState>,
+    restore_fn: Option<RestoreFn>,
+    #[cfg(feature = "input")]
+    esc_timeout: Duration,
+    /// The modes set with `set_application_*`, whose reset is registered with `defer`
+    #[cfg(feature = "sequences")]
+    reset_modes: u8,
+    /// How many levels `push_kitty_keyboard` pushed, shared with the step popping them on drop
+    #[cfg(feature = "sequences")]
+    kitty_pushes: Arc<AtomicU16>,
+    /// How many times a write is retried after `EINTR` or `EAGAIN`
+    write_retries: u32,
+}
+
+/// The state of a [`RawTerminal`], as returned by [`RawTerminal::state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawState {
+    /// Raw mode is active.
+    Raw,
+    /// The previous attributes are applied by
+    /// [`suspend_raw_mode`](RawTerminal::suspend_raw_mode), until
+    /// [`activate_raw_mode`](RawTerminal::activate_raw_mode).
+    Suspended,
+    /// The terminal was restored by [`restore`](RawTerminal::restore), so dropping the
+    /// `RawTerminal` does nothing more.
+    Restored,
 }
 
+/// What [`RawTerminal::restore`] achieved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RestoreOutcome {
+    /// The saved attributes were applied.
+    Restored,
+    /// Applying the attributes failed with `EIO` or `EBADF`: the terminal is gone, e.g. because
+    /// the ssh session or terminal emulator was closed, so there was nothing left to restore.
+    TerminalGone,
+}
+
+/// Bits of `RawTerminal::reset_modes`
+#[cfg(feature = "sequences")]
+const APP_CURSOR_KEYS: u8 = 1;
+#[cfg(feature = "sequences")]
+const APP_KEYPAD: u8 = 2;
+#[cfg(feature = "sequences")]
+const BRACKETED_PASTE: u8 = 4;
+#[cfg(feature = "sequences")]
+const FOCUS_REPORTING: u8 = 8;
+#[cfg(feature = "sequences")]
+const KITTY_KEYBOARD: u8 = 16;
+
+/// A teardown step registered with [`RawTerminal::defer`]
+type Teardown<W> = Box<dyn FnOnce(&mut W) -> io::Result<()> + Send>;
+
+/// A replacement for `tcsetattr` registered with [`RawTerminal::set_restore_fn`]
+type RestoreFn = Box<dyn FnMut(BorrowedFd<'_>, &Termios) -> io::Result<()> + Send>;
+
 impl<W: Write + AsFd> Drop for RawTerminal<W> {
     fn drop(&mut self) {
-        let _ = set_terminal_attr(self.output.as_fd(), &self.prev_ios);
+        if self.state.get() != RawState::Restored {
+            let _result = self.restore();
+            #[cfg(feature = "log")]
+            log::debug!(
+                "restoring fd {} on drop: {:?}",
+                self.control_fd().as_raw_fd(),
+                _result
+            );
+        }
+        if !self.output_taken {
+            // SAFETY: the output is still there, and never used again
+            unsafe { ManuallyDrop::drop(&mut self.output) }
+        }
+    }
+}
+
+/// Shows the control fd and a summary of the saved attributes, without requiring `W: Debug`.
+impl<W: Write + AsFd> fmt::Debug for RawTerminal<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawTerminal")
+            .field("fd", &self.control_fd().as_raw_fd())
+            .field("separate_control_fd", &self.control.is_some())
+            .field("state", &self.state.get())
+            .field("saved_iflag", &format_args!("{:#x}", self.prev_ios.c_iflag))
+            .field("saved_oflag", &format_args!("{:#x}", self.prev_ios.c_oflag))
+            .field("saved_cflag", &format_args!("{:#x}", self.prev_ios.c_cflag))
+            .field("saved_lflag", &format_args!("{:#x}", self.prev_ios.c_lflag))
+            .field("deferred_steps", &self.deferred.len())
+            .field("custom_restore", &self.restore_fn.is_some())
+            .finish_non_exhaustive()
     }
 }
 
@@ -66,7 +339,9 @@ impl<W: Write + AsFd> ops::DerefMut for RawTerminal<W> {
 
 impl<W: Write + AsFd> Write for RawTerminal<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.output.write(buf)
+        retry_write(self.write_retries, &mut *self.output, |output| {
+            output.write(buf)
+        })
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -74,6 +349,72 @@ impl<W: Write + AsFd> Write for RawTerminal<W> {
     }
 }
 
+/// Writing through a shared reference, for writers like [`Stdout`](io::Stdout) or
+/// [`File`](std::fs::File) that support it.
+///
+/// ```rust
+/// # use std::{fs::File, io::Read, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # assert_eq!(ret, 0);
+/// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// use termion_raw2::IntoRawMode;
+/// use std::io::Write;
+///
+/// let tty = tty.into_raw_mode()?;
+/// write!(&tty, "hi")?;
+/// # let mut buf = [0; 2];
+/// # master.read_exact(&mut buf)?;
+/// # assert_eq!(&buf, b"hi");
+/// # std::io::Result::Ok(())
+/// ```
+impl<W: Write + AsFd> Write for &RawTerminal<W>
+where
+    for<'a> &'a W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        retry_write(self.write_retries, &mut &*self.output, |output| {
+            output.write(buf)
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self.output).flush()
+    }
+}
+
+/// A control character, unless it is disabled
+fn saved_char(c: libc::cc_t) -> Option<u8> {
+    Some(c).filter(|&c| c != ControlChars::DISABLED)
+}
+
+/// How long a write waits for a non-blocking fd to become writable before retrying
+const WRITE_RETRY_WAIT: Duration = Duration::from_millis(50);
+
+/// Call `write` until it succeeds, retrying `EINTR` and `EAGAIN` up to `retries` times.
+///
+/// Before retrying `EAGAIN`, waits up to `WRITE_RETRY_WAIT` for `output` to become writable.
+fn retry_write<T: AsFd>(
+    retries: u32,
+    output: &mut T,
+    mut write: impl FnMut(&mut T) -> io::Result<usize>,
+) -> io::Result<usize> {
+    let mut attempts = 0;
+    loop {
+        match write(output) {
+            Err(e) if attempts < retries => match e.kind() {
+                io::ErrorKind::Interrupted => {}
+                io::ErrorKind::WouldBlock => {
+                    wait_writable(output.as_fd(), Some(WRITE_RETRY_WAIT))?;
+                }
+                _ => return Err(e),
+            },
+            result => return result,
+        }
+        attempts += 1;
+    }
+}
+
 #[cfg(unix)]
 mod unix_impl {
     use super::*;
@@ -85,6 +426,31 @@ mod unix_impl {
         }
     }
 
+    /// Lets a shared reference be passed where `impl AsRawFd` is expected.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let tty = unsafe { File::from_raw_fd(slave) };
+    /// use termion_raw2::IntoRawMode;
+    /// use std::os::fd::AsRawFd;
+    ///
+    /// fn fd_of(fd: impl AsRawFd) -> i32 {
+    ///     fd.as_raw_fd()
+    /// }
+    ///
+    /// let tty = tty.into_raw_mode()?;
+    /// assert_eq!(fd_of(&tty), tty.as_raw_fd());
+    /// # std::io::Result::Ok(())
+    /// ```
+    impl<W: Write + AsFd + AsRawFd> AsRawFd for &RawTerminal<W> {
+        fn as_raw_fd(&self) -> RawFd {
+            self.output.as_raw_fd()
+        }
+    }
+
     impl<W: Write + AsFd> AsFd for RawTerminal<W> {
         fn as_fd(&self) -> BorrowedFd<'_> {
             self.output.as_fd()
@@ -92,6 +458,72 @@ mod unix_impl {
     }
 }
 
+/// Check whether the terminal behind `fd` already is in a raw-like mode.
+///
+/// This is a heuristic, since there is no single definition of raw mode. The terminal counts as
+/// raw if
+///
+/// 1. canonical mode (`ICANON`) is off, so input isn't line buffered,
+/// 2. echo (`ECHO`) is off, and
+/// 3. `VMIN` is 0 or 1, so a read returns as soon as a single byte is available (or on `VTIME`
+///    timeout).
+///
+/// Other flags, like signal generation or output processing, are not taken into account. This
+/// is useful to avoid entering raw mode twice, or to detect state inherited from a parent.
+///
+/// ```rust,no_run
+/// use termion_raw2::{is_raw, IntoRawMode};
+/// use std::io::stdout;
+///
+/// if !is_raw(&stdout())? {
+///     let stdout = stdout().into_raw_mode()?;
+/// }
+/// # std::io::Result::Ok(())
+/// ```
+pub fn is_raw<F: AsFd>(fd: &F) -> io::Result<bool> {
+    let ios = get_terminal_attr(fd.as_fd())?;
+    Ok(ios.c_lflag & (libc::ICANON | libc::ECHO) == 0 && ios.c_cc[libc::VMIN] <= 1)
+}
+
+/// Take a snapshot of the current attributes of the terminal behind `fd`.
+///
+/// The snapshot can be compared against later, or made the restore target of a
+/// [`RawTerminal`] entered later with
+/// [`into_raw_mode_restoring_to`](IntoRawMode::into_raw_mode_restoring_to).
+pub fn capture_state<F: AsFd>(fd: &F) -> io::Result<TermiosSnapshot> {
+    get_terminal_attr(fd.as_fd()).map(TermiosSnapshot::from)
+}
+
+/// Check whether `a` and `b` refer to the same terminal.
+///
+/// This compares the device numbers from `fstat`, so the same terminal opened twice, or through
+/// different paths, counts as the same. `/dev/tty` is a device of its own, though, and doesn't
+/// match the terminal it stands for. If either fd isn't a terminal, the answer is `false`.
+///
+/// Useful for deciding e.g. whether a prompt written to stderr shows up on the terminal that
+/// input is read from.
+///
+/// ```rust
+/// # use std::{fs::File, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # assert_eq!(ret, 0);
+/// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// use termion_raw2::same_terminal;
+///
+/// assert!(same_terminal(&tty, &tty.try_clone()?)?);
+/// let file = File::open(std::env::current_exe()?)?;
+/// assert!(!same_terminal(&tty, &file)?);
+/// # std::io::Result::Ok(())
+/// ```
+pub fn same_terminal<A: AsFd, B: AsFd>(a: &A, b: &B) -> io::Result<bool> {
+    let (a, b) = (a.as_fd(), b.as_fd());
+    if !is_tty(a) || !is_tty(b) {
+        return Ok(false);
+    }
+    Ok(device(a)? == device(b)?)
+}
+
 /// Types which can be converted into "raw mode".
 ///
 /// # Why is this type defined on writers and not readers?
@@ -105,36 +537,1276 @@ pub trait IntoRawMode: Write + AsFd + Sized {
     /// the program). Furthermore, the input isn't canonicalised or buffered (that is, you can
     /// read from stdin one byte of a time). The output is neither modified in any way.
     fn into_raw_mode(self) -> io::Result<RawTerminal<Self>>;
+
+    /// Switch to raw mode, telling apart the common reasons for failure.
+    ///
+    /// [`into_raw_mode`](Self::into_raw_mode) is the same, with the error converted back to an
+    /// [`io::Error`].
+    ///
+    /// ```rust,no_run
+    /// use termion_raw2::{IntoRawMode, RawModeError};
+    /// use std::io::stdout;
+    ///
+    /// match stdout().try_into_raw_mode() {
+    ///     Ok(stdout) => { /* interactive mode */ }
+    ///     Err(RawModeError::NotATty) => { /* output is redirected, fall back to plain output */ }
+    ///     Err(err) => return Err(err.into()),
+    /// }
+    /// # std::io::Result::Ok(())
+    /// ```
+    fn try_into_raw_mode(self) -> Result<RawTerminal<Self>, RawModeError>;
+
+    /// Switch to raw mode, controlling the terminal through a separate file descriptor.
+    ///
+    /// All terminal attribute calls go to `control`, while writes still go to `self`. This is
+    /// useful when the output is redirected but the terminal is still reachable, e.g. through
+    /// `/dev/tty`.
+    ///
+    /// The returned [`RawTerminal`] takes ownership of `control`: the old attributes are
+    /// restored through it on drop, after which it is closed.
+    ///
+    /// ```rust,no_run
+    /// use termion_raw2::IntoRawMode;
+    /// use std::{fs::File, io::stdout};
+    ///
+    /// let tty = File::open("/dev/tty")?;
+    /// let stdout = stdout().into_raw_mode_with_control_fd(tty.into())?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    fn into_raw_mode_with_control_fd(self, control: OwnedFd) -> io::Result<RawTerminal<Self>>;
+
+    /// Switch to raw mode, discarding any input that was typed ahead.
+    ///
+    /// The attributes are applied with [`SetMode::Flush`] (`TCSAFLUSH`) instead of
+    /// [`SetMode::Now`] (`TCSANOW`): pending output is transmitted first, and input received but
+    /// not read yet is thrown away. Use this for prompts like passwords, where keystrokes typed
+    /// before the prompt appeared must not be taken as the answer.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr, thread, time::Duration};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// # master.write_all(b"typed ahead")?;
+    /// # thread::sleep(Duration::from_millis(50));
+    /// let tty = tty.into_raw_mode_flushing()?;
+    /// assert_eq!(tty.bytes_available()?, 0);
+    /// # std::io::Result::Ok(())
+    /// ```
+    fn into_raw_mode_flushing(self) -> io::Result<RawTerminal<Self>>;
+
+    /// Switch to raw mode, controlling the terminal through a duplicate of the writer's fd.
+    ///
+    /// The duplicate is owned by the returned [`RawTerminal`] and closed on drop, after the old
+    /// attributes are restored through it. Restoring therefore still works if the writer's own fd
+    /// is closed or replaced in the meantime, e.g. by `dup2` redirections.
+    ///
+    /// This only isolates the *handle* used to restore. Terminal attributes belong to the
+    /// terminal device, not to a file descriptor, so changes made through any other fd of the
+    /// same terminal are still seen and overwritten by the restore.
+    fn into_raw_mode_isolated(self) -> io::Result<RawTerminal<Self>>;
+
+    /// Switch to raw mode with some of the usual behavior kept, as described by `config`.
+    ///
+    /// This is the same as the corresponding [`RawModeBuilder`] calls, for when the options are
+    /// data, e.g. read from a configuration file.
+    fn into_raw_mode_with_config(self, config: RawConfig) -> io::Result<RawTerminal<Self>>;
+
+    /// Switch to raw mode, restoring `snapshot` instead of the current attributes on drop.
+    ///
+    /// Raw mode is derived from the current attributes as usual; only the restore target
+    /// changes. [`suspend_raw_mode`](RawTerminal::suspend_raw_mode),
+    /// [`erase_char`](RawTerminal::erase_char) and [`kill_char`](RawTerminal::kill_char) still
+    /// go by the attributes found on entering raw mode. This suits layered ownership, e.g. a host capturing the terminal state with
+    /// [`capture_state`] at startup, so that the terminal goes back to exactly that state when
+    /// a plugin entering raw mode later is torn down, whatever happened in between.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::{AsRawFd, FromRawFd}, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// # let probe = tty.try_clone()?;
+    /// use termion_raw2::{capture_state, IntoRawMode};
+    ///
+    /// let host = capture_state(&tty)?;
+    /// // Something changes the terminal in between, here turning off echo
+    /// # unsafe {
+    /// #     let mut ios = std::mem::zeroed();
+    /// #     libc::tcgetattr(tty.as_raw_fd(), &mut ios);
+    /// #     ios.c_lflag &= !libc::ECHO;
+    /// #     libc::tcsetattr(tty.as_raw_fd(), libc::TCSANOW, &ios);
+    /// # }
+    /// let plugin = tty.into_raw_mode_restoring_to(host)?;
+    /// // Suspending goes back to the attributes found on entering, with echo still off
+    /// plugin.suspend_raw_mode()?;
+    /// assert_ne!(capture_state(&probe)?, host);
+    /// drop(plugin);
+    /// assert_eq!(capture_state(&probe)?, host);
+    /// # std::io::Result::Ok(())
+    /// ```
+    fn into_raw_mode_restoring_to(self, snapshot: TermiosSnapshot)
+        -> io::Result<RawTerminal<Self>>;
+
+    /// Compute what [`into_raw_mode`](Self::into_raw_mode) would do, without changing anything.
+    ///
+    /// Returns the current attributes and the ones raw mode would apply. Only `tcgetattr` is
+    /// called; the raw attributes are computed in memory.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let tty = unsafe { File::from_raw_fd(slave) };
+    /// use termion_raw2::{IntoRawMode, OutputFlags};
+    ///
+    /// let (current, raw) = tty.preview()?;
+    /// assert!(current.output_flags().contains(OutputFlags::OPOST));
+    /// assert!(!raw.output_flags().contains(OutputFlags::OPOST));
+    /// # std::io::Result::Ok(())
+    /// ```
+    fn preview(&self) -> io::Result<(TermiosSnapshot, TermiosSnapshot)>;
 }
 
 impl<W: Write + AsFd> IntoRawMode for W {
     fn into_raw_mode(self) -> io::Result<RawTerminal<W>> {
-        let mut ios = get_terminal_attr(self.as_fd())?;
+        RawTerminal::enter(self, None, SetMode::Now, raw_terminal_attr)
+    }
+
+    fn try_into_raw_mode(self) -> Result<RawTerminal<W>, RawModeError> {
+        Ok(RawTerminal::enter(
+            self,
+            None,
+            SetMode::Now,
+            raw_terminal_attr,
+        )?)
+    }
+
+    fn into_raw_mode_with_control_fd(self, control: OwnedFd) -> io::Result<RawTerminal<W>> {
+        RawTerminal::enter(self, Some(control), SetMode::Now, raw_terminal_attr)
+    }
+
+    fn into_raw_mode_flushing(self) -> io::Result<RawTerminal<W>> {
+        RawTerminal::enter(self, None, SetMode::Flush, raw_terminal_attr)
+    }
+
+    fn preview(&self) -> io::Result<(TermiosSnapshot, TermiosSnapshot)> {
+        let current = get_terminal_attr(self.as_fd())?;
+        let mut raw = current;
+        raw_terminal_attr(&mut raw);
+        Ok((current.into(), raw.into()))
+    }
+
+    fn into_raw_mode_isolated(self) -> io::Result<RawTerminal<W>> {
+        let control = self.as_fd().try_clone_to_owned()?;
+        RawTerminal::enter(self, Some(control), SetMode::Now, raw_terminal_attr)
+    }
+
+    fn into_raw_mode_with_config(self, config: RawConfig) -> io::Result<RawTerminal<W>> {
+        RawTerminal::enter(self, None, SetMode::Now, |ios| config.apply_to(ios))
+    }
+
+    fn into_raw_mode_restoring_to(self, snapshot: TermiosSnapshot) -> io::Result<RawTerminal<W>> {
+        let mut term = RawTerminal::enter(self, None, SetMode::Now, raw_terminal_attr)?;
+        term.restore_ios = Some(*snapshot.as_termios());
+        Ok(term)
+    }
+}
+
+impl<W: Write + AsFd> RawTerminal<W> {
+    /// Switch `writer` to raw mode, checking every step on the way
+    ///
+    /// Tells apart exactly which step failed: `writer` not being a terminal
+    /// ([`RawModeError::NotATty`]), setting the attributes (the variant for its `errno`), and
+    /// the terminal not honoring them when they are read back with
+    /// [`verify_raw`](Self::verify_raw) ([`RawModeError::NotHonored`]). In the last case the
+    /// previous attributes are restored before returning.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{RawModeError, RawTerminal};
+    /// use std::fs::OpenOptions;
+    ///
+    /// let tty = RawTerminal::try_new(tty)?;
+    /// assert!(tty.verify_raw()?);
+    ///
+    /// let null = OpenOptions::new().write(true).open("/dev/null")?;
+    /// assert!(matches!(RawTerminal::try_new(null), Err(RawModeError::NotATty)));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn try_new(writer: W) -> Result<Self, RawModeError> {
+        if !is_tty(writer.as_fd()) {
+            return Err(RawModeError::NotATty);
+        }
+        let term = RawTerminal::enter(writer, None, SetMode::Now, raw_terminal_attr)?;
+        if !term.verify_raw()? {
+            return Err(RawModeError::NotHonored);
+        }
+        Ok(term)
+    }
+
+    fn enter(
+        output: W,
+        control: Option<OwnedFd>,
+        mode: SetMode,
+        configure: impl FnOnce(&mut Termios),
+    ) -> io::Result<Self> {
+        let fd = match &control {
+            Some(fd) => fd.as_fd(),
+            None => output.as_fd(),
+        };
+        let mut ios = get_terminal_attr(fd)?;
         let prev_ios = ios;
 
-        raw_terminal_attr(&mut ios);
+        configure(&mut ios);
 
-        set_terminal_attr(self.as_fd(), &ios)?;
+        set_terminal_attr_with(fd, mode, &ios)?;
 
         Ok(RawTerminal {
             prev_ios,
-            output: self,
+            restore_ios: None,
+            raw_ios: ios,
+            cache_attrs: true,
+            output: ManuallyDrop::new(output),
+            output_taken: false,
+            control,
+            signal_restore: None,
+            deferred: Vec::new(),
+            state: Cell::new(RawState::Raw),
+            restore_fn: None,
+            #[cfg(feature = "input")]
+            esc_timeout: keys::ESC_TIMEOUT,
+            #[cfg(feature = "sequences")]
+            reset_modes: 0,
+            #[cfg(feature = "sequences")]
+            kitty_pushes: Arc::new(AtomicU16::new(0)),
+            write_retries: 0,
         })
     }
-}
 
-impl<W: Write + AsFd> RawTerminal<W> {
+    /// The attributes restored on drop
+    fn restore_target(&self) -> &Termios {
+        self.restore_ios.as_ref().unwrap_or(&self.prev_ios)
+    }
+
+    /// The file descriptor used to control the terminal attributes
+    fn control_fd(&self) -> BorrowedFd<'_> {
+        match &self.control {
+            Some(fd) => fd.as_fd(),
+            None => self.output.as_fd(),
+        }
+    }
+
     /// Temporarily switch to original mode
+    ///
+    /// This is a single `tcsetattr` call with the attributes saved on entering raw mode, and
+    /// [`activate_raw_mode`](Self::activate_raw_mode) afterwards is another one.
     pub fn suspend_raw_mode(&self) -> io::Result<()> {
-        set_terminal_attr(self.as_fd(), &self.prev_ios)?;
+        set_terminal_attr(self.control_fd(), &self.prev_ios)?;
+        self.state.set(RawState::Suspended);
         Ok(())
     }
 
     /// Temporarily switch to raw mode
+    ///
+    /// This applies cached raw mode attributes: the ones applied on entering raw mode, including
+    /// later changes made through this `RawTerminal`. If other code changed the attributes in the
+    /// meantime, call [`refresh_cache`](Self::refresh_cache) first. After
+    /// [`suspend_raw_mode`](Self::suspend_raw_mode), this is a single `tcsetattr` call without
+    /// reading the attributes first:
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{is_raw, IntoRawMode};
+    ///
+    /// let tty = tty.into_raw_mode()?;
+    /// tty.suspend_raw_mode()?;
+    /// assert!(!is_raw(&tty)?);
+    /// tty.activate_raw_mode()?;
+    /// assert!(is_raw(&tty)?);
+    /// # std::io::Result::Ok(())
+    /// ```
+    ///
+    /// With caching turned off by [`set_attr_caching`](Self::set_attr_caching), the current
+    /// attributes are read and made raw instead.
+    ///
+    /// Otherwise, e.g. when raw mode is already active or caching is off, the current attributes
+    /// are read first, and `tcsetattr` is only called if they differ, so activating raw mode
+    /// again is a no-op.
     pub fn activate_raw_mode(&self) -> io::Result<()> {
-        let mut ios = get_terminal_attr(self.as_fd())?;
-        raw_terminal_attr(&mut ios);
-        set_terminal_attr(self.as_fd(), &ios)?;
+        if self.cache_attrs && self.state.get() == RawState::Suspended {
+            set_terminal_attr(self.control_fd(), &self.raw_ios)?;
+        } else if self.cache_attrs {
+            set_terminal_attr_if_changed(self.control_fd(), &self.raw_ios)?;
+        } else {
+            let current = get_terminal_attr(self.control_fd())?;
+            let mut ios = current;
+            raw_terminal_attr(&mut ios);
+            if !attrs_equal(&current, &ios) {
+                set_terminal_attr(self.control_fd(), &ios)?;
+            }
+        }
+        self.state.set(RawState::Raw);
+        Ok(())
+    }
+
+    /// Whether raw mode is active, suspended or restored
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{IntoRawMode, RawState};
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// assert_eq!(tty.state(), RawState::Raw);
+    /// tty.suspend_raw_mode()?;
+    /// assert_eq!(tty.state(), RawState::Suspended);
+    /// tty.activate_raw_mode()?;
+    /// assert_eq!(tty.state(), RawState::Raw);
+    /// tty.restore()?;
+    /// assert_eq!(tty.state(), RawState::Restored);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn state(&self) -> RawState {
+        self.state.get()
+    }
+
+    /// Restore the terminal now, instead of on drop
+    ///
+    /// This does everything dropping does: it runs the steps registered with
+    /// [`defer`](Self::defer), flushes the output and applies the saved attributes. Afterwards
+    /// the state is [`RawState::Restored`] and dropping does nothing more, unless raw mode is
+    /// activated again with [`activate_raw_mode`](Self::activate_raw_mode). Unlike on drop,
+    /// errors from flushing and setting the attributes are returned.
+    ///
+    /// If the terminal has disconnected, setting the attributes fails with `EIO` (or `EBADF`).
+    /// That isn't reported as an error but as [`RestoreOutcome::TerminalGone`], and errors from
+    /// flushing are ignored then, as they have the same cause.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::{IntoRawMode, RestoreOutcome};
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// // Closing the other end of a pseudo terminal hangs it up
+    /// drop(master);
+    /// assert_eq!(tty.restore()?, RestoreOutcome::TerminalGone);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn restore(&mut self) -> io::Result<RestoreOutcome> {
+        while let Some(step) = self.deferred.pop() {
+            let _ = step(&mut self.output);
+        }
+        let flushed = self.output.flush();
+        let fd = match &self.control {
+            Some(fd) => fd.as_fd(),
+            None => self.output.as_fd(),
+        };
+        let target = self.restore_ios.as_ref().unwrap_or(&self.prev_ios);
+        let set = match &mut self.restore_fn {
+            Some(restore) => restore(fd, target),
+            None => set_terminal_attr(fd, target),
+        };
+        let outcome = match set {
+            Ok(()) => RestoreOutcome::Restored,
+            Err(e) if matches!(e.raw_os_error(), Some(libc::EIO | libc::EBADF)) => {
+                RestoreOutcome::TerminalGone
+            }
+            Err(e) => return Err(e),
+        };
+        self.state.set(RawState::Restored);
+        match outcome {
+            RestoreOutcome::Restored => flushed.and(Ok(outcome)),
+            RestoreOutcome::TerminalGone => Ok(outcome),
+        }
+    }
+
+    /// Restore the attributes with `f` instead of `tcsetattr`
+    ///
+    /// `f` is called with the control fd and the saved attributes whenever the terminal is
+    /// restored for good: on drop, by [`restore`](Self::restore), and by the guard returned from
+    /// [`split`](Self::split). This allows injecting logging or simulated failures, or restoring
+    /// through some other interface. [`suspend_raw_mode`](Self::suspend_raw_mode) is not
+    /// affected.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// # let probe = tty.try_clone()?;
+    /// use termion_raw2::{is_raw, IntoRawMode};
+    /// use std::io;
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// tty.set_restore_fn(|_fd, _ios| Err(io::Error::other("simulated failure")));
+    /// assert!(tty.restore().is_err());
+    /// assert!(is_raw(&probe)?);
+    /// # tty.forget_restore();
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn set_restore_fn(
+        &mut self,
+        f: impl FnMut(BorrowedFd<'_>, &Termios) -> io::Result<()> + Send + 'static,
+    ) {
+        self.restore_fn = Some(Box::new(f));
+    }
+
+    /// Check that the terminal is actually in the raw mode that was requested
+    ///
+    /// Reads the attributes back and compares everything raw mode is about (echo, canonical mode,
+    /// signals, input and output processing, character size, `VMIN` and `VTIME`) with what was
+    /// set on entering raw mode, including later changes made through this `RawTerminal`.
+    /// Returns `false` if the terminal didn't honor them, as some broken or remote terminals do,
+    /// or if raw mode is currently suspended.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// let tty = tty.into_raw_mode()?;
+    /// assert!(tty.verify_raw()?);
+    /// tty.suspend_raw_mode()?;
+    /// assert!(!tty.verify_raw()?);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn verify_raw(&self) -> io::Result<bool> {
+        let current = get_terminal_attr(self.control_fd())?;
+        Ok(raw_attrs_match(&current, &self.raw_ios))
+    }
+
+    /// Turn caching of the raw mode attributes on or off
+    ///
+    /// Caching is on by default and assumes the control fd keeps referring to the same terminal.
+    /// Turn it off for writers whose fd may change to a different terminal, see
+    /// [the type level docs](Self#file-descriptors-that-change).
+    pub fn set_attr_caching(&mut self, enabled: bool) {
+        self.cache_attrs = enabled;
+    }
+
+    /// Cache the current attributes as the ones [`activate_raw_mode`](Self::activate_raw_mode)
+    /// applies
+    ///
+    /// Only needed when code outside of this `RawTerminal` changed the attributes while raw mode
+    /// was active, and those changes should survive suspending. Call it while raw mode is active.
+    pub fn refresh_cache(&mut self) -> io::Result<()> {
+        self.raw_ios = get_terminal_attr(self.control_fd())?;
         Ok(())
     }
+
+    /// Enable or disable software flow control (`IXON`/`IXOFF`)
+    ///
+    /// With flow control enabled, Ctrl-S and Ctrl-Q pause and resume the output instead of being
+    /// delivered as input. The previous setting is restored on drop along with everything else.
+    pub fn set_flow_control(&mut self, enabled: bool) -> io::Result<()> {
+        self.modify_attrs(|ios| {
+            if enabled {
+                ios.c_iflag |= libc::IXON | libc::IXOFF;
+            } else {
+                ios.c_iflag &= !(libc::IXON | libc::IXOFF);
+            }
+        })
+    }
+
+    /// Modify the special control characters, e.g. to disable `VINTR` or remap `VEOF`
+    ///
+    /// The current characters are read, passed to `f` and written back. The previous characters
+    /// are restored on drop along with everything else.
+    ///
+    /// ```rust,no_run
+    /// use termion_raw2::{ControlChars, IntoRawMode};
+    /// use std::io::stdout;
+    ///
+    /// let mut stdout = stdout().into_raw_mode()?;
+    /// stdout.modify_control_chars(|cc| cc.intr = ControlChars::DISABLED)?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn modify_control_chars(&mut self, f: impl FnOnce(&mut ControlChars)) -> io::Result<()> {
+        self.modify_attrs(|ios| {
+            let mut cc = ControlChars::from_termios(ios);
+            f(&mut cc);
+            cc.apply_to(ios);
+        })
+    }
+
+    /// The erase character (`VERASE`) configured before raw mode, or `None` if it is disabled
+    ///
+    /// Raw mode turns off line editing, so the terminal no longer acts on this character, but a
+    /// custom line editor can use it to treat the key the way the user is used to. It is usually
+    /// DEL (`0x7f`) or Ctrl-H (`0x08`).
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// let tty = tty.into_raw_mode()?;
+    /// assert_eq!(tty.erase_char(), Some(0x7f));
+    /// assert_eq!(tty.kill_char(), Some(0x15));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn erase_char(&self) -> Option<u8> {
+        saved_char(self.prev_ios.c_cc[libc::VERASE])
+    }
+
+    /// The kill character (`VKILL`) configured before raw mode, or `None` if it is disabled
+    ///
+    /// It erases the current line in canonical mode, and is usually Ctrl-U (`0x15`). See
+    /// [`erase_char`](Self::erase_char).
+    pub fn kill_char(&self) -> Option<u8> {
+        saved_char(self.prev_ios.c_cc[libc::VKILL])
+    }
+
+    /// The path of the terminal device, e.g. `/dev/pts/3`
+    ///
+    /// Fails with `ENOTTY` if the control fd doesn't refer to a terminal.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let tty = unsafe { File::from_raw_fd(slave) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// let tty = tty.into_raw_mode()?;
+    /// assert!(tty.tty_name()?.starts_with("/dev/"));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn tty_name(&self) -> io::Result<PathBuf> {
+        tty_name(self.control_fd())
+    }
+
+    /// Modify the input processing flags
+    ///
+    /// The previous flags are restored on drop along with everything else.
+    ///
+    /// ```rust,no_run
+    /// use termion_raw2::{InputFlags, IntoRawMode};
+    /// use std::io::stdout;
+    ///
+    /// let mut stdout = stdout().into_raw_mode()?;
+    /// // Check parity and mark bytes with parity errors in the input
+    /// stdout.modify_input_flags(|flags| flags.insert(InputFlags::INPCK | InputFlags::PARMRK))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn modify_input_flags(&mut self, f: impl FnOnce(&mut InputFlags)) -> io::Result<()> {
+        self.modify_attrs(|ios| {
+            let mut flags = InputFlags::from_bits_retain(ios.c_iflag);
+            f(&mut flags);
+            ios.c_iflag = flags.bits();
+        })
+    }
+
+    /// Modify the output processing flags
+    ///
+    /// The previous flags are restored on drop along with everything else.
+    ///
+    /// ```rust,no_run
+    /// use termion_raw2::{IntoRawMode, OutputFlags};
+    /// use std::io::stdout;
+    ///
+    /// let mut stdout = stdout().into_raw_mode()?;
+    /// // Let the kernel translate `\n` into `\r\n`
+    /// stdout.modify_output_flags(|flags| flags.insert(OutputFlags::OPOST | OutputFlags::ONLCR))?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn modify_output_flags(&mut self, f: impl FnOnce(&mut OutputFlags)) -> io::Result<()> {
+        self.modify_attrs(|ios| {
+            let mut flags = OutputFlags::from_bits_retain(ios.c_oflag);
+            f(&mut flags);
+            ios.c_oflag = flags.bits();
+        })
+    }
+
+    /// The number of input bytes that can be read right now without blocking (`FIONREAD`)
+    ///
+    /// Note that this counts bytes in the terminal's input queue, as seen through the control
+    /// fd; data already buffered by a reader in userspace isn't included. In canonical mode some
+    /// platforms only count bytes of completed lines, and a few don't support `FIONREAD` on
+    /// terminals at all, in which case an error is returned.
+    pub fn bytes_available(&self) -> io::Result<usize> {
+        bytes_available(self.control_fd())
+    }
+
+    /// Whether this process is in the foreground process group of the terminal
+    ///
+    /// Compares `tcgetpgrp` on the control fd with the process's own group. A background process
+    /// that writes to the terminal or changes its attributes gets `SIGTTOU`, which stops it by
+    /// default (writes only if `TOSTOP` is set). Fails if the terminal isn't the controlling
+    /// terminal of this process.
+    ///
+    /// ```rust,no_run
+    /// use termion_raw2::IntoRawMode;
+    /// use std::io::{stdout, Write};
+    ///
+    /// let mut stdout = stdout().into_raw_mode()?;
+    /// if stdout.is_foreground()? {
+    ///     stdout.write_all(b"status: ok")?;
+    /// }
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn is_foreground(&self) -> io::Result<bool> {
+        let foreground = foreground_pgrp(self.control_fd())?;
+        Ok(foreground == unsafe { libc::getpgrp() })
+    }
+
+    /// The line discipline of the terminal (`TIOCGETD`)
+    ///
+    /// The line discipline is the kernel layer that processes the terminal's data. On Linux the
+    /// values include `N_TTY` (0, the normal terminal discipline), `N_SLIP` (1), `N_MOUSE` (2),
+    /// `N_PPP` (3), `N_HDLC` (13) and `N_GSM0710` (21), see `<linux/tty.h>`. On the BSDs and
+    /// macOS, 0 is likewise the normal terminal discipline (`TTYDISC`).
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// assert_eq!(tty.line_discipline()?, 0);
+    /// tty.restore_line_discipline_on_drop()?;
+    /// tty.set_line_discipline(0)?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn line_discipline(&self) -> io::Result<i32> {
+        sys::tty::line_discipline(self.control_fd())
+    }
+
+    /// Set the line discipline of the terminal (`TIOCSETD`)
+    ///
+    /// See [`line_discipline`](Self::line_discipline) for the values. Most disciplines other
+    /// than the normal one need privileges, e.g. `CAP_NET_ADMIN` for `N_SLIP` on Linux. Unlike
+    /// the attributes, the previous discipline isn't restored on drop, unless
+    /// [`restore_line_discipline_on_drop`](Self::restore_line_discipline_on_drop) is called
+    /// first.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn set_line_discipline(&mut self, ldisc: i32) -> io::Result<()> {
+        sys::tty::set_line_discipline(self.control_fd(), ldisc)
+    }
+
+    /// Restore the current line discipline on drop
+    ///
+    /// The discipline is read now and set again, with a step registered with
+    /// [`defer`](Self::defer), before the attributes are restored.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn restore_line_discipline_on_drop(&mut self) -> io::Result<()> {
+        let ldisc = self.line_discipline()?;
+        let fd = self.control_fd().try_clone_to_owned()?;
+        self.defer(move |_| sys::tty::set_line_discipline(fd.as_fd(), ldisc));
+        Ok(())
+    }
+
+    /// Duplicates of the control fd, to use as the stdin, stdout and stderr of a child process
+    ///
+    /// Each [`Stdio`](process::Stdio) owns its own duplicate, which is closed once the child
+    /// has been spawned (or when it is dropped unused), so this `RawTerminal` and the terminal
+    /// state it restores are unaffected. The child sees the terminal in whatever mode it is in;
+    /// call [`suspend_raw_mode`](Self::suspend_raw_mode) first for a child that expects a
+    /// normal terminal.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Read, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    /// use std::process::Command;
+    ///
+    /// let tty = tty.into_raw_mode()?;
+    /// let (stdin, stdout, stderr) = tty.child_stdio()?;
+    /// let status = Command::new("sh")
+    ///     .args(["-c", "printf hi"])
+    ///     .stdin(stdin)
+    ///     .stdout(stdout)
+    ///     .stderr(stderr)
+    ///     .status()?;
+    /// assert!(status.success());
+    /// # let mut buf = [0; 2];
+    /// # master.read_exact(&mut buf)?;
+    /// # assert_eq!(&buf, b"hi");
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn child_stdio(&self) -> io::Result<(process::Stdio, process::Stdio, process::Stdio)> {
+        let fd = self.control_fd();
+        Ok((
+            fd.try_clone_to_owned()?.into(),
+            fd.try_clone_to_owned()?.into(),
+            fd.try_clone_to_owned()?.into(),
+        ))
+    }
+
+    /// Wait until input can be read, or `timeout` elapses
+    ///
+    /// Returns whether input is ready, polling the control fd. With `None` it waits
+    /// indefinitely. If the wait is interrupted by a signal, it resumes with the time that is
+    /// left. Like [`bytes_available`](Self::bytes_available), this doesn't know about data
+    /// already buffered by a reader in userspace.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Write, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    /// use std::time::Duration;
+    ///
+    /// let tty = tty.into_raw_mode()?;
+    /// assert!(!tty.wait_readable(Some(Duration::from_millis(10)))?);
+    /// # master.write_all(b"x")?;
+    /// assert!(tty.wait_readable(Some(Duration::from_secs(1)))?);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn wait_readable(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        wait_readable(self.control_fd(), timeout)
+    }
+
+    /// Wait until output can be written, or `timeout` elapses
+    ///
+    /// Returns whether the terminal is ready for output, polling the control fd for `POLLOUT`.
+    /// It isn't while the output buffer is full, e.g. because output was paused with XOFF
+    /// (Ctrl-S) under flow control, or a serial line is slower than the program. With `None` it
+    /// waits indefinitely. If the wait is interrupted by a signal, it resumes with the time that
+    /// is left.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    /// use std::time::Duration;
+    ///
+    /// let tty = tty.into_raw_mode()?;
+    /// assert!(tty.wait_writable(Some(Duration::from_millis(10)))?);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn wait_writable(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        wait_writable(self.control_fd(), timeout)
+    }
+
+    /// Modify the local flags, which control echo, line editing and signals
+    ///
+    /// The previous flags are restored on drop along with everything else.
+    pub fn modify_local_flags(&mut self, f: impl FnOnce(&mut LocalFlags)) -> io::Result<()> {
+        self.modify_attrs(|ios| {
+            let mut flags = LocalFlags::from_bits_retain(ios.c_lflag);
+            f(&mut flags);
+            ios.c_lflag = flags.bits();
+        })
+    }
+
+    /// Set whether echoed control characters are shown as `^X` ([`LocalFlags::ECHOCTL`])
+    ///
+    /// Only has a visible effect while echo is on, e.g. within [`with_echo`](Self::with_echo).
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+        target_os = "illumos",
+    ))]
+    pub fn set_echo_ctl(&mut self, enabled: bool) -> io::Result<()> {
+        self.modify_local_flags(|flags| flags.set(LocalFlags::ECHOCTL, enabled))
+    }
+
+    /// Set the input and output speed to any baud rate, not just one of the `B*` constants
+    ///
+    /// This goes through the Linux specific `termios2` interface (`TCGETS2`/`TCSETS2` with
+    /// `BOTHER`), which the C library's `tcsetattr` doesn't expose. It is only available on Linux,
+    /// and not on PowerPC, whose kernel interface differs. Whether the speed is actually honored
+    /// is up to the driver; pseudo terminals accept and ignore it.
+    ///
+    /// The previous speed is restored on drop along with everything else. Because the speed
+    /// can't be represented in a plain [`Termios`], [`activate_raw_mode`](Self::activate_raw_mode)
+    /// after [`suspend_raw_mode`](Self::suspend_raw_mode) doesn't bring it back; call this again
+    /// instead.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// tty.set_custom_baud(250_000)?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[cfg(all(
+        target_os = "linux",
+        not(any(target_arch = "powerpc", target_arch = "powerpc64"))
+    ))]
+    pub fn set_custom_baud(&mut self, baud: u32) -> io::Result<()> {
+        sys::attr::set_custom_baud(self.control_fd(), baud)
+    }
+
+    /// Modify the hardware control flags, e.g. to configure a serial line
+    ///
+    /// The previous flags are restored on drop along with everything else.
+    ///
+    /// ```rust,no_run
+    /// use termion_raw2::{CharSize, ControlFlags, IntoRawMode};
+    /// use std::fs::OpenOptions;
+    ///
+    /// let serial = OpenOptions::new().read(true).write(true).open("/dev/ttyUSB0")?;
+    /// let mut serial = serial.into_raw_mode()?;
+    /// // 7E1
+    /// serial.modify_control_flags(|flags| {
+    ///     flags.set_char_size(CharSize::Seven);
+    ///     flags.insert(ControlFlags::PARENB | ControlFlags::CREAD | ControlFlags::CLOCAL);
+    ///     flags.remove(ControlFlags::PARODD | ControlFlags::CSTOPB);
+    /// })?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn modify_control_flags(&mut self, f: impl FnOnce(&mut ControlFlags)) -> io::Result<()> {
+        self.modify_attrs(|ios| {
+            let mut flags = ControlFlags::from_bits_retain(ios.c_cflag);
+            f(&mut flags);
+            ios.c_cflag = flags.bits();
+        })
+    }
+
+    /// Read the current attributes, let `f` modify them and write them back
+    ///
+    /// The result is cached, so that it survives suspending and activating raw mode.
+    fn modify_attrs(&mut self, f: impl FnOnce(&mut Termios)) -> io::Result<()> {
+        self.try_modify_attrs(|ios| {
+            f(ios);
+            Ok(())
+        })
+    }
+
+    /// Like `modify_attrs`, leaving the attributes alone if `f` fails
+    fn try_modify_attrs(
+        &mut self,
+        f: impl FnOnce(&mut Termios) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut ios = get_terminal_attr(self.control_fd())?;
+        f(&mut ios)?;
+        set_terminal_attr(self.control_fd(), &ios)?;
+        self.raw_ios = ios;
+        Ok(())
+    }
+
+    /// Run `f` with echo enabled, then turn echo back off
+    ///
+    /// The previous echo state is restored even if `f` returns an error or panics.
+    pub fn with_echo<F, R>(&mut self, f: F) -> io::Result<R>
+    where
+        F: FnOnce(&mut Self) -> io::Result<R>,
+    {
+        self.with_attrs(|ios| ios.c_lflag |= libc::ECHO, f)
+    }
+
+    /// Apply `configure` to the current attributes for the duration of `f`
+    ///
+    /// The attributes from before are restored afterwards, even if `f` returns an error, and by
+    /// a guard if it panics. An error from `f` takes precedence over an error restoring the
+    /// attributes. Unlike [`modify_local_flags`](Self::modify_local_flags) and friends, the
+    /// change isn't cached, so it doesn't survive suspending and activating raw mode within `f`.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// # let oflag = |fd| unsafe {
+    /// #     let mut ios = std::mem::zeroed();
+    /// #     assert_eq!(libc::tcgetattr(fd, &mut ios), 0);
+    /// #     ios.c_oflag
+    /// # };
+    /// use termion_raw2::IntoRawMode;
+    /// use std::os::fd::AsRawFd;
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// // Turn output processing back on, e.g. to translate `\n` to `\r\n`, for a while
+    /// tty.with_attrs(
+    ///     |ios| ios.c_oflag |= libc::OPOST,
+    ///     |tty| {
+    ///         assert_ne!(oflag(tty.as_raw_fd()) & libc::OPOST, 0);
+    ///         Ok(())
+    ///     },
+    /// )?;
+    /// assert_eq!(oflag(tty.as_raw_fd()) & libc::OPOST, 0);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn with_attrs<F, R>(&mut self, configure: impl FnOnce(&mut Termios), f: F) -> io::Result<R>
+    where
+        F: FnOnce(&mut Self) -> io::Result<R>,
+    {
+        let saved = get_terminal_attr(self.control_fd())?;
+        let mut ios = saved;
+        configure(&mut ios);
+        set_terminal_attr(self.control_fd(), &ios)?;
+
+        let guard = AttrGuard { term: self, saved };
+        let result = f(guard.term);
+        let restored = set_terminal_attr(guard.term.control_fd(), &guard.saved);
+        mem::forget(guard);
+
+        let value = result?;
+        restored?;
+        Ok(value)
+    }
+
+    /// Give back the inner writer without restoring the previous terminal state
+    ///
+    /// The terminal stays in whatever mode it is currently in. Restoring it, if ever, becomes the
+    /// responsibility of the caller, e.g. on a signal or before exit. A separate control fd given
+    /// with [`IntoRawMode::into_raw_mode_with_control_fd`] is closed, and handlers installed with
+    /// [`install_signal_restore`](Self::install_signal_restore) are uninstalled. Steps registered
+    /// with [`defer`](Self::defer) are dropped without running.
+    pub fn forget_restore(self) -> W {
+        self.into_output()
+    }
+
+    /// Take out the writer, dropping everything else without restoring the terminal
+    fn into_output(mut self) -> W {
+        self.state.set(RawState::Restored);
+        self.output_taken = true;
+        // SAFETY: `output_taken` keeps `Drop` from touching the output again
+        unsafe { ManuallyDrop::take(&mut self.output) }
+    }
+
+    /// Register a teardown step to run on drop
+    ///
+    /// Steps run in the reverse order they were registered, before the output is flushed and the
+    /// terminal attributes are restored. This makes the order of teardown explicit instead of
+    /// depending on the order values are dropped in. Errors returned by a step are ignored, and
+    /// the remaining steps still run.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Read, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    /// use std::io::Write;
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// tty.defer(|out| out.write_all(b"1"));
+    /// tty.defer(|out| out.write_all(b"2"));
+    /// drop(tty);
+    /// # let mut buf = [0; 2];
+    /// # master.read_exact(&mut buf)?;
+    /// # assert_eq!(&buf, b"21");
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn defer(&mut self, step: impl FnOnce(&mut W) -> io::Result<()> + Send + 'static) {
+        self.deferred.push(Box::new(step));
+    }
+
+    /// Wrap the terminal in a [`CrlfWriter`], so that `\n` starts a new line again
+    pub fn with_crlf(self) -> CrlfWriter<Self> {
+        CrlfWriter::new(self)
+    }
+
+    /// Retry writes that fail with `EINTR` or `EAGAIN`, up to `retries` times
+    ///
+    /// By default (`retries` of 0), writing just forwards to the inner writer, so an interrupted
+    /// write, or one to a non-blocking fd whose buffer is full, fails. With retries enabled, an
+    /// interrupted write is retried right away. If the fd is non-blocking and the write would
+    /// block, it waits up to 50 ms for the fd to become writable first. Once the retries are
+    /// used up, the last error is returned.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Read, os::fd::{AsRawFd, FromRawFd}, ptr, thread};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// # assert_eq!(unsafe { libc::fcntl(tty.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK) }, 0);
+    /// use termion_raw2::IntoRawMode;
+    /// use std::io::Write;
+    ///
+    /// // `tty` is non-blocking, and much more is written than fits into its buffer
+    /// let mut tty = tty.into_raw_mode()?;
+    /// tty.set_write_retries(100);
+    /// # let reader = thread::spawn(move || master.read_exact(&mut [0; 1 << 20]));
+    /// tty.write_all(&[b'x'; 1 << 20])?;
+    /// # reader.join().unwrap()?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn set_write_retries(&mut self, retries: u32) {
+        self.write_retries = retries;
+    }
+
+    /// Wrap the terminal in a [`ThrottledWriter`], so that output is paced to
+    /// `bytes_per_second`
+    pub fn with_throttle(self, bytes_per_second: Option<NonZeroU32>) -> ThrottledWriter<Self> {
+        ThrottledWriter::new(self, bytes_per_second)
+    }
+
+    /// Wrap the terminal in a [`LineFlushWriter`], so that output is delivered a line at a time
+    pub fn with_line_flush(self) -> LineFlushWriter<Self> {
+        LineFlushWriter::new(self)
+    }
+
+    /// Wrap the terminal in a [`TrackingWriter`], so that the cursor position is estimated
+    ///
+    /// The size of the terminal is set on the writer if it can be queried, so that wrapping is
+    /// tracked too.
+    #[cfg(feature = "sequences")]
+    pub fn with_tracking(self) -> TrackingWriter<Self> {
+        let size = self.size().ok();
+        let mut writer = TrackingWriter::new(self);
+        writer.set_size(size);
+        writer
+    }
+
+    /// Wrap the terminal in an [`AutoFlush`], so that output appears as soon as it is written
+    pub fn with_auto_flush(self) -> AutoFlush<Self> {
+        AutoFlush::new(self)
+    }
+
+    /// Run `f` with the cursor position saved, moving the cursor back afterwards
+    ///
+    /// Writes [`SAVE_CURSOR`](sequences::SAVE_CURSOR), runs `f`, which may move the cursor and
+    /// write, then writes [`RESTORE_CURSOR`](sequences::RESTORE_CURSOR) and flushes. The cursor
+    /// is moved back even if `f` fails, in which case the error from `f` is returned. This suits
+    /// e.g. updating a status line without disturbing the main output.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Read, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    /// use std::io::Write;
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// tty.with_saved_cursor(|tty| write!(tty, "\x1b[1;1Hstatus"))?;
+    /// # let mut buf = [0; 18];
+    /// # master.read_exact(&mut buf)?;
+    /// # assert_eq!(&buf, b"\x1b[s\x1b[1;1Hstatus\x1b[u");
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[cfg(feature = "sequences")]
+    pub fn with_saved_cursor<F, R>(&mut self, f: F) -> io::Result<R>
+    where
+        F: FnOnce(&mut Self) -> io::Result<R>,
+    {
+        self.output.write_all(sequences::SAVE_CURSOR.as_bytes())?;
+        let result = f(self);
+        let restored = self.write_seq(sequences::RESTORE_CURSOR);
+
+        let value = result?;
+        restored?;
+        Ok(value)
+    }
+
+    /// Make the cursor keys send application sequences, or normal ones
+    ///
+    /// Writes [`APP_CURSOR_KEYS_ON`](sequences::APP_CURSOR_KEYS_ON) or
+    /// [`APP_CURSOR_KEYS_OFF`](sequences::APP_CURSOR_KEYS_OFF). In application mode the arrow
+    /// keys send `ESC O A` and so on instead of `ESC [ A`; [`read_key`](Self::read_key)
+    /// understands both. Once enabled, the mode is reset to normal on drop, like the other
+    /// managed modes, before the attributes are restored.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, io::Read, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// let mut tty = tty.into_raw_mode()?;
+    /// tty.set_application_cursor_keys(true)?;
+    /// drop(tty);
+    /// # let mut buf = [0; 10];
+    /// # master.read_exact(&mut buf)?;
+    /// # assert_eq!(&buf, b"\x1b[?1h\x1b[?1l");
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[cfg(feature = "sequences")]
+    pub fn set_application_cursor_keys(&mut self, enabled: bool) -> io::Result<()> {
+        self.set_managed_mode(
+            APP_CURSOR_KEYS,
+            enabled,
+            sequences::APP_CURSOR_KEYS_ON,
+            sequences::APP_CURSOR_KEYS_OFF,
+        )
+    }
+
+    /// Make the numeric keypad send application sequences, or normal characters
+    ///
+    /// Writes [`APP_KEYPAD_ON`](sequences::APP_KEYPAD_ON) or
+    /// [`APP_KEYPAD_OFF`](sequences::APP_KEYPAD_OFF). Once enabled, the mode is reset to normal
+    /// on drop, like [`set_application_cursor_keys`](Self::set_application_cursor_keys).
+    #[cfg(feature = "sequences")]
+    pub fn set_application_keypad(&mut self, enabled: bool) -> io::Result<()> {
+        self.set_managed_mode(
+            APP_KEYPAD,
+            enabled,
+            sequences::APP_KEYPAD_ON,
+            sequences::APP_KEYPAD_OFF,
+        )
+    }
+
+    /// Turn bracketed paste mode on or off
+    ///
+    /// Writes [`BRACKETED_PASTE_ON`](sequences::BRACKETED_PASTE_ON) or
+    /// [`BRACKETED_PASTE_OFF`](sequences::BRACKETED_PASTE_OFF), so that pastes are read as
+    /// [`Event::Paste`]. Once enabled, the mode is turned off on drop.
+    #[cfg(feature = "sequences")]
+    pub fn set_bracketed_paste(&mut self, enabled: bool) -> io::Result<()> {
+        self.set_managed_mode(
+            BRACKETED_PASTE,
+            enabled,
+            sequences::BRACKETED_PASTE_ON,
+            sequences::BRACKETED_PASTE_OFF,
+        )
+    }
+
+    /// Turn focus reporting on or off
+    ///
+    /// Writes [`FOCUS_REPORTING_ON`](sequences::FOCUS_REPORTING_ON) or
+    /// [`FOCUS_REPORTING_OFF`](sequences::FOCUS_REPORTING_OFF), so that focus changes are read
+    /// as [`Event::FocusGained`] and [`Event::FocusLost`]. Once enabled, the mode is turned off
+    /// on drop.
+    #[cfg(feature = "sequences")]
+    pub fn set_focus_reporting(&mut self, enabled: bool) -> io::Result<()> {
+        self.set_managed_mode(
+            FOCUS_REPORTING,
+            enabled,
+            sequences::FOCUS_REPORTING_ON,
+            sequences::FOCUS_REPORTING_OFF,
+        )
+    }
+
+    /// Switch a mode on or off, registering its reset with `defer` the first time it is enabled
+    #[cfg(feature = "sequences")]
+    fn set_managed_mode(
+        &mut self,
+        mode: u8,
+        enabled: bool,
+        on: &'static str,
+        off: &'static str,
+    ) -> io::Result<()> {
+        self.write_seq(if enabled { on } else { off })?;
+        if enabled && self.reset_modes & mode == 0 {
+            self.reset_modes |= mode;
+            self.defer(move |out| out.write_all(off.as_bytes()));
+        }
+        Ok(())
+    }
+
+    /// Write an escape sequence (usually one from [`sequences`]) and flush the output
+    #[cfg(feature = "sequences")]
+    pub fn write_seq(&mut self, seq: &str) -> io::Result<()> {
+        self.output.write_all(seq.as_bytes())?;
+        self.output.flush()
+    }
+}
+
+/// Restores attributes saved by [`RawTerminal::with_attrs`] if the closure panics
+struct AttrGuard<'a, W: Write + AsFd> {
+    term: &'a mut RawTerminal<W>,
+    saved: Termios,
+}
+
+impl<W: Write + AsFd> Drop for AttrGuard<'_, W> {
+    fn drop(&mut self) {
+        let _ = set_terminal_attr(self.term.control_fd(), &self.saved);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use crate::sys::sim::call_counts;
+    use crate::IntoRawMode;
+
+    /// Any fd serves as a terminal, as the attributes are simulated in unit tests
+    fn terminal() -> File {
+        File::options().write(true).open("/dev/null").unwrap()
+    }
+
+    #[test]
+    fn activate_after_suspend_only_sets() {
+        let tty = terminal().into_raw_mode().unwrap();
+        let (gets, sets) = call_counts();
+        tty.suspend_raw_mode().unwrap();
+        tty.activate_raw_mode().unwrap();
+        assert_eq!(call_counts(), (gets, sets + 2));
+    }
+
+    #[test]
+    fn activate_when_raw_reads_but_doesnt_set() {
+        let tty = terminal().into_raw_mode().unwrap();
+        let (gets, sets) = call_counts();
+        tty.activate_raw_mode().unwrap();
+        assert_eq!(call_counts(), (gets + 1, sets));
+    }
 }
@@ -0,0 +1,172 @@
+//! Parsing mouse reports.
+
+/// A mouse event, as reported with mouse reporting enabled, e.g. by
+/// [`MOUSE_SGR_ON`](crate::sequences::MOUSE_SGR_ON).
+///
+/// More fields may be added in the future; use [`MouseEvent::new`] to create one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct MouseEvent {
+    /// The button involved, if known. Legacy (X10) reports don't say which button was
+    /// released, and moving without a button held has none.
+    pub button: Option<MouseButton>,
+    /// The column, starting at 1.
+    pub x: u16,
+    /// The row, starting at 1.
+    pub y: u16,
+    /// What happened.
+    pub kind: MouseEventKind,
+}
+
+impl MouseEvent {
+    /// Create a mouse event, e.g. to compare a parsed one against.
+    pub fn new(button: Option<MouseButton>, x: u16, y: u16, kind: MouseEventKind) -> Self {
+        MouseEvent { button, x, y, kind }
+    }
+}
+
+/// A mouse button, including the directions of the scroll wheel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MouseButton {
+    /// The left button.
+    Left,
+    /// The middle button.
+    Middle,
+    /// The right button.
+    Right,
+    /// Scrolling up.
+    WheelUp,
+    /// Scrolling down.
+    WheelDown,
+    /// Scrolling left.
+    WheelLeft,
+    /// Scrolling right.
+    WheelRight,
+}
+
+/// What a [`MouseEvent`] reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MouseEventKind {
+    /// A button was pressed, or the wheel scrolled.
+    Press,
+    /// A button was released.
+    Release,
+    /// The mouse moved with a button held.
+    Drag,
+    /// The mouse moved without a button held, only reported if enabled (`\x1b[?1003h`).
+    Move,
+}
+
+/// Parse a mouse report at the start of `bytes`.
+///
+/// Both the SGR encoding (`ESC [ < b ; x ; y M`, or `m` on release) and the legacy X10 encoding
+/// (`ESC [ M` followed by three bytes) are understood. Returns the event and the number of bytes
+/// it takes up, or `None` if `bytes` doesn't start with a complete mouse report.
+///
+/// ```rust
+/// use termion_raw2::{parse_mouse, MouseButton, MouseEvent, MouseEventKind};
+///
+/// let (event, len) = parse_mouse(b"\x1b[<0;12;5Mrest").unwrap();
+/// assert_eq!(len, 10);
+/// assert_eq!(
+///     event,
+///     MouseEvent::new(Some(MouseButton::Left), 12, 5, MouseEventKind::Press),
+/// );
+/// assert_eq!(parse_mouse(b"\x1b[<0;12"), None);
+/// ```
+pub fn parse_mouse(bytes: &[u8]) -> Option<(MouseEvent, usize)> {
+    parse_mouse_prefix(bytes).flatten()
+}
+
+/// Parse a mouse report at the start of `buf`.
+///
+/// Returns `None` if the report is incomplete, and `Some(None)` if `buf` doesn't start with a
+/// valid one.
+pub(crate) fn parse_mouse_prefix(buf: &[u8]) -> Option<Option<(MouseEvent, usize)>> {
+    match buf {
+        [0x1b, b'[', b'<', rest @ ..] => {
+            parse_sgr(rest).map(|parsed| parsed.map(|(event, len)| (event, len + 3)))
+        }
+        [0x1b, b'[', b'M', rest @ ..] => match rest {
+            [cb, cx, cy, ..] => Some(parse_x10(*cb, *cx, *cy).map(|event| (event, 6))),
+            _ => None,
+        },
+        _ if b"\x1b[<".starts_with(buf) || b"\x1b[M".starts_with(buf) => None,
+        _ => Some(None),
+    }
+}
+
+/// Parse the part of an SGR report after `ESC [ <`.
+fn parse_sgr(buf: &[u8]) -> Option<Option<(MouseEvent, usize)>> {
+    // Three numbers of at most five digits, and the separators
+    const MAX_LEN: usize = 3 * 5 + 2;
+
+    let Some(end) = buf.iter().position(|b| !matches!(b, b'0'..=b'9' | b';')) else {
+        return (buf.len() > MAX_LEN).then_some(None);
+    };
+    let release = match buf[end] {
+        b'M' => false,
+        b'm' => true,
+        _ => return Some(None),
+    };
+    let mut numbers = buf[..end]
+        .split(|&b| b == b';')
+        .map(|n| std::str::from_utf8(n).ok()?.parse::<u16>().ok());
+    let event = match (
+        numbers.next(),
+        numbers.next(),
+        numbers.next(),
+        numbers.next(),
+    ) {
+        (Some(Some(cb)), Some(Some(x)), Some(Some(y)), None) => decode(cb, release, x, y),
+        _ => None,
+    };
+    Some(event.map(|event| (event, end + 1)))
+}
+
+/// Parse the three bytes following `ESC [ M` in a legacy report, each offset by 32.
+fn parse_x10(cb: u8, cx: u8, cy: u8) -> Option<MouseEvent> {
+    let (cb, x, y) = (
+        cb.checked_sub(32)?,
+        cx.checked_sub(32)?,
+        cy.checked_sub(32)?,
+    );
+    // Without a button, this is a release unless it is a move
+    let release = cb & (64 | 32 | 3) == 3;
+    decode(cb.into(), release, x.into(), y.into())
+}
+
+/// Decode the button byte of a report.
+fn decode(cb: u16, release: bool, x: u16, y: u16) -> Option<MouseEvent> {
+    // Shift, Alt and Ctrl
+    const MODIFIERS: u16 = 4 | 8 | 16;
+    const MOTION: u16 = 32;
+
+    let button = match cb & !(MODIFIERS | MOTION) {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Middle),
+        2 => Some(MouseButton::Right),
+        3 => None,
+        64 => Some(MouseButton::WheelUp),
+        65 => Some(MouseButton::WheelDown),
+        66 => Some(MouseButton::WheelLeft),
+        67 => Some(MouseButton::WheelRight),
+        _ => return None,
+    };
+    let kind = if release {
+        MouseEventKind::Release
+    } else if cb & MOTION != 0 {
+        match button {
+            Some(_) => MouseEventKind::Drag,
+            None => MouseEventKind::Move,
+        }
+    } else if button.is_none() {
+        // A press of no button in the SGR encoding
+        return None;
+    } else {
+        MouseEventKind::Press
+    };
+    Some(MouseEvent { button, x, y, kind })
+}
@@ -0,0 +1,115 @@
+//! A process wide registry of terminals to restore.
+
+use std::{
+    io,
+    os::fd::{AsFd, OwnedFd},
+    sync::{Mutex, Once, PoisonError, TryLockError},
+};
+
+use crate::sys::attr::{get_terminal_attr, set_terminal_attr};
+use crate::sys::tty::file_id;
+use crate::Termios;
+
+/// A registered terminal: its own fd, the attributes to restore, and its device and inode
+type Registration = (OwnedFd, Termios, (libc::dev_t, libc::ino_t));
+
+/// The registered terminals, at most one per terminal.
+static REGISTRY: Mutex<Vec<Registration>> = Mutex::new(Vec::new());
+
+/// Save the current attributes of the terminal behind `fd`, to be restored by [`restore_all`].
+///
+/// This is a safety net for programs where it's hard to make sure every [`RawTerminal`] is
+/// dropped on every path to exit, independent of the individual guards. Register each terminal
+/// before changing its mode. The fd is duplicated, so the registration stays valid even if `fd`
+/// is closed.
+///
+/// Each terminal is registered only once, told apart by device and inode: registering one
+/// again, e.g. through another fd or for every [`RawTerminal`], keeps the attributes saved first
+/// and doesn't hold on to another fd.
+///
+/// ```rust
+/// # use std::{fs::File, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe {
+/// #     libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null())
+/// # };
+/// # assert_eq!(ret, 0);
+/// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// # let probe = tty.try_clone()?;
+/// use termion_raw2::{is_raw, register_restore, restore_all, IntoRawMode};
+///
+/// register_restore(&tty)?;
+/// // Leave raw mode on, as a missed guard would
+/// tty.into_raw_mode()?.forget_restore();
+/// assert!(is_raw(&probe)?);
+/// restore_all()?;
+/// assert!(!is_raw(&probe)?);
+///
+/// // Registering in raw mode again doesn't replace the cooked attributes saved first
+/// let tty = probe.into_raw_mode()?;
+/// register_restore(&*tty)?;
+/// restore_all()?;
+/// assert!(!is_raw(&*tty)?);
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// [`RawTerminal`]: crate::RawTerminal
+pub fn register_restore<F: AsFd>(fd: &F) -> io::Result<()> {
+    let fd = fd.as_fd();
+    let id = file_id(fd)?;
+    let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+    if registry.iter().any(|&(_, _, other)| other == id) {
+        return Ok(());
+    }
+    let ios = get_terminal_attr(fd)?;
+    registry.push((fd.try_clone_to_owned()?, ios, id));
+    Ok(())
+}
+
+/// Restore every terminal registered with [`register_restore`].
+///
+/// Terminals are restored in the reverse order they were registered. All terminals are restored
+/// even if some fail, and the first error is returned. The registrations are kept, so this can be
+/// called again.
+pub fn restore_all() -> io::Result<()> {
+    let registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+    restore(&registry)
+}
+
+/// Call [`restore_all`] when the process exits normally, through `atexit`.
+///
+/// This covers returning from `main` and [`std::process::exit`], but not being killed by a
+/// signal (see [`RawTerminal::install_signal_restore`](crate::RawTerminal::install_signal_restore))
+/// or aborting. Calling it more than once registers the callback only once. If another thread
+/// holds the registry while the process exits, restoring is skipped rather than risking a
+/// deadlock. A registry poisoned by a panic is still restored.
+pub fn restore_all_at_exit() -> io::Result<()> {
+    static REGISTER: Once = Once::new();
+
+    extern "C" fn at_exit() {
+        let registry = match REGISTRY.try_lock() {
+            Ok(registry) => registry,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return,
+        };
+        let _ = restore(&registry);
+    }
+
+    let mut ret = 0;
+    REGISTER.call_once(|| ret = unsafe { libc::atexit(at_exit) });
+    if ret != 0 {
+        return Err(io::Error::other("failed to register an atexit callback"));
+    }
+    Ok(())
+}
+
+fn restore(registry: &[Registration]) -> io::Result<()> {
+    let mut result = Ok(());
+    for (fd, ios, _) in registry.iter().rev() {
+        let restored = set_terminal_attr(fd.as_fd(), ios);
+        if result.is_ok() {
+            result = restored;
+        }
+    }
+    result
+}
@@ -0,0 +1,199 @@
+//! Scripted and recorded input, for testing.
+
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    os::fd::{AsFd, BorrowedFd},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::sys::pipe::pipe;
+
+/// A sequence of input bytes and pauses, played back by a [`ScriptedReader`].
+///
+/// ```rust
+/// use termion_raw2::{events, Event, Key, Script};
+/// use std::time::Duration;
+///
+/// // Sent in one go, `ESC [ A` is the up arrow
+/// let mut keys = events(Script::new().send(b"\x1b[A").play()?);
+/// assert_eq!(keys.next().unwrap()?, Event::Key(Key::Up));
+/// assert!(keys.next().is_none());
+///
+/// // With a pause longer than the escape timeout, it is Esc followed by `[` and `A`
+/// let script = Script::new()
+///     .send(b"\x1b")
+///     .pause(Duration::from_millis(200))
+///     .send(b"[A");
+/// let keys: Vec<_> = events(script.play()?).collect::<Result<_, _>>()?;
+/// assert_eq!(
+///     keys,
+///     [Key::Esc, Key::Char('['), Key::Char('A')].map(Event::Key),
+/// );
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Send(Vec<u8>),
+    Pause(Duration),
+}
+
+impl Script {
+    /// Create an empty script.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send `bytes`, in a single write.
+    pub fn send(mut self, bytes: impl AsRef<[u8]>) -> Self {
+        self.steps.push(Step::Send(bytes.as_ref().to_vec()));
+        self
+    }
+
+    /// Wait for `duration` before the next step.
+    pub fn pause(mut self, duration: Duration) -> Self {
+        self.steps.push(Step::Pause(duration));
+        self
+    }
+
+    /// All the bytes the script sends, without the timing.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::Send(bytes) => Some(&bytes[..]),
+                Step::Pause(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .concat()
+    }
+
+    /// Start playing the script, returning the reader the input arrives at.
+    ///
+    /// The input is fed into a pipe by a thread, so the reader has a real file descriptor that
+    /// can be polled, and pauses in the script are seen as time without input, like on a
+    /// terminal. Once the script is done, the reader gets the end of input.
+    pub fn play(self) -> io::Result<ScriptedReader> {
+        let (read, write) = pipe(false)?;
+        let mut write = File::from(write);
+        thread::Builder::new()
+            .name("termion-raw2 script".into())
+            .spawn(move || {
+                for step in self.steps {
+                    match step {
+                        // Fails once the reader is dropped
+                        Step::Send(bytes) => {
+                            if write.write_all(&bytes).is_err() {
+                                break;
+                            }
+                        }
+                        Step::Pause(duration) => thread::sleep(duration),
+                    }
+                }
+            })?;
+        Ok(ScriptedReader {
+            pipe: File::from(read),
+        })
+    }
+}
+
+/// A reader playing back a [`Script`], returned by [`Script::play`].
+///
+/// Dropping it stops the playback.
+#[derive(Debug)]
+pub struct ScriptedReader {
+    pipe: File,
+}
+
+impl Read for ScriptedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.pipe.read(buf)
+    }
+}
+
+impl AsFd for ScriptedReader {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.pipe.as_fd()
+    }
+}
+
+/// A reader recording what is read through it, for later replay.
+///
+/// Every read is recorded as it arrived, with the time since the previous one, so
+/// [`script`](Self::script) reproduces the timing that matters for telling an `ESC` key from an
+/// escape sequence.
+///
+/// ```rust
+/// use termion_raw2::{RecordingReader, Script};
+/// use std::io::Read;
+///
+/// let mut input = RecordingReader::new(&b"typed"[..]);
+/// input.read_exact(&mut [0; 2])?;
+/// input.read_exact(&mut [0; 3])?;
+/// assert_eq!(input.recorded(), b"typed");
+/// assert_eq!(input.script().bytes(), b"typed");
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct RecordingReader<R> {
+    reader: R,
+    last: Instant,
+    script: Script,
+}
+
+impl<R> RecordingReader<R> {
+    /// Wrap `reader`, starting the recording.
+    pub fn new(reader: R) -> Self {
+        RecordingReader {
+            reader,
+            last: Instant::now(),
+            script: Script::new(),
+        }
+    }
+
+    /// All the bytes read so far.
+    pub fn recorded(&self) -> Vec<u8> {
+        self.script.bytes()
+    }
+
+    /// The input read so far as a script, with pauses as they happened.
+    pub fn script(&self) -> Script {
+        self.script.clone()
+    }
+
+    /// Get a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Unwrap the inner reader and the recording.
+    pub fn into_parts(self) -> (R, Script) {
+        (self.reader, self.script)
+    }
+}
+
+impl<R: Read> Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        if n > 0 {
+            let now = Instant::now();
+            let pause = now - self.last;
+            self.last = now;
+            let script = std::mem::take(&mut self.script);
+            self.script = script.pause(pause).send(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+impl<R: AsFd> AsFd for RecordingReader<R> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.reader.as_fd()
+    }
+}
@@ -0,0 +1,73 @@
+//! Escape sequences commonly used together with raw mode.
+//!
+//! These are plain `&'static str` constants for those who prefer to manage escape sequences
+//! themselves. They can be written with [`RawTerminal::write_seq`](crate::RawTerminal::write_seq),
+//! which also flushes the output. [`FullscreenTerminal`](crate::FullscreenTerminal) uses the same
+//! constants for its setup and teardown.
+
+/// Resets all graphic rendition attributes (colors, bold, underline, etc.).
+pub const RESET: &str = "\x1b[0m";
+
+/// Clears the entire screen. The cursor position is not changed.
+pub const CLEAR_SCREEN: &str = "\x1b[2J";
+
+/// Hides the cursor.
+pub const HIDE_CURSOR: &str = "\x1b[?25l";
+
+/// Shows the cursor.
+pub const SHOW_CURSOR: &str = "\x1b[?25h";
+
+/// Saves the cursor position, to be restored by [`RESTORE_CURSOR`].
+pub const SAVE_CURSOR: &str = "\x1b[s";
+
+/// Moves the cursor back to the position saved by [`SAVE_CURSOR`].
+pub const RESTORE_CURSOR: &str = "\x1b[u";
+
+/// Switches to the alternate screen buffer.
+pub const ALT_SCREEN_ENTER: &str = "\x1b[?1049h";
+
+/// Switches back to the main screen buffer.
+pub const ALT_SCREEN_LEAVE: &str = "\x1b[?1049l";
+
+/// Makes the cursor keys send application sequences (`ESC O A` instead of `ESC [ A`).
+pub const APP_CURSOR_KEYS_ON: &str = "\x1b[?1h";
+
+/// Makes the cursor keys send normal sequences again.
+pub const APP_CURSOR_KEYS_OFF: &str = "\x1b[?1l";
+
+/// Makes the numeric keypad send application sequences.
+pub const APP_KEYPAD_ON: &str = "\x1b=";
+
+/// Makes the numeric keypad send normal characters again.
+pub const APP_KEYPAD_OFF: &str = "\x1b>";
+
+/// Enables bracketed paste mode: pasted text is surrounded by `\x1b[200~` and `\x1b[201~`.
+pub const BRACKETED_PASTE_ON: &str = "\x1b[?2004h";
+
+/// Disables bracketed paste mode.
+pub const BRACKETED_PASTE_OFF: &str = "\x1b[?2004l";
+
+/// Enables focus reporting: the terminal sends `\x1b[I` when it gains focus and `\x1b[O` when
+/// it loses it.
+pub const FOCUS_REPORTING_ON: &str = "\x1b[?1004h";
+
+/// Disables focus reporting.
+pub const FOCUS_REPORTING_OFF: &str = "\x1b[?1004l";
+
+/// Enables mouse button reporting using the SGR extended encoding.
+pub const MOUSE_SGR_ON: &str = "\x1b[?1000h\x1b[?1006h";
+
+/// Disables mouse reporting enabled by [`MOUSE_SGR_ON`].
+pub const MOUSE_SGR_OFF: &str = "\x1b[?1006l\x1b[?1000l";
+
+/// Pops one level of keyboard flags pushed with `ESC [ > flags u`, returning to the previous
+/// keyboard protocol. See [`RawTerminal::push_kitty_keyboard`](crate::RawTerminal::push_kitty_keyboard).
+pub const KITTY_KEYBOARD_POP: &str = "\x1b[<u";
+
+/// Asks for the current keyboard flags. Terminals supporting the kitty keyboard protocol reply
+/// with `ESC [ ? flags u`, others ignore it.
+pub const KITTY_KEYBOARD_QUERY: &str = "\x1b[?u";
+
+/// Asks for the primary device attributes. Nearly every terminal replies with
+/// `ESC [ ? ... c`, which makes it useful to tell when replies to other queries aren't coming.
+pub const DEVICE_ATTRIBUTES_QUERY: &str = "\x1b[c";
@@ -0,0 +1,184 @@
+//! Restoring the terminal from signal handlers.
+
+use std::{
+    cell::UnsafeCell,
+    io::{self, Write},
+    mem::MaybeUninit,
+    os::fd::{AsFd, AsRawFd},
+    sync::atomic::{AtomicI32, AtomicU8, Ordering},
+};
+
+use crate::sys::signal::{reset_handler, set_handler};
+use crate::{RawTerminal, Termios};
+
+/// A signal that terminates the process by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signal {
+    /// `SIGINT`, usually sent by Ctrl-C (if signals are enabled).
+    Int,
+    /// `SIGTERM`, the default signal of `kill`.
+    Term,
+    /// `SIGHUP`, sent when the terminal is closed.
+    Hup,
+    /// `SIGQUIT`, usually sent by Ctrl-\\ (if signals are enabled).
+    Quit,
+    /// `SIGABRT`, raised by `abort`, e.g. through [`std::process::abort`] or a panic while
+    /// panicking.
+    Abort,
+    /// `SIGSEGV`, an invalid memory access.
+    Segv,
+    /// `SIGBUS`, a bus error, e.g. accessing a truncated memory mapped file.
+    Bus,
+}
+
+impl Signal {
+    fn as_raw(self) -> libc::c_int {
+        match self {
+            Signal::Int => libc::SIGINT,
+            Signal::Term => libc::SIGTERM,
+            Signal::Hup => libc::SIGHUP,
+            Signal::Quit => libc::SIGQUIT,
+            Signal::Abort => libc::SIGABRT,
+            Signal::Segv => libc::SIGSEGV,
+            Signal::Bus => libc::SIGBUS,
+        }
+    }
+}
+
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const READY: u8 = 2;
+
+/// The attributes to restore from a signal handler.
+///
+/// A signal handler can't take locks, so access is coordinated through `state`: the fd and the
+/// attributes are only written while `state` is `WRITING`, and only read once it is `READY`.
+struct Saved {
+    state: AtomicU8,
+    fd: AtomicI32,
+    ios: UnsafeCell<MaybeUninit<Termios>>,
+}
+
+// SAFETY: `ios` is only accessed as described on `Saved`
+unsafe impl Sync for Saved {}
+
+static SAVED: Saved = Saved {
+    state: AtomicU8::new(EMPTY),
+    fd: AtomicI32::new(-1),
+    ios: UnsafeCell::new(MaybeUninit::uninit()),
+};
+
+extern "C" fn restore_and_reraise(signal: libc::c_int) {
+    // Only async-signal-safe calls from here on
+    if SAVED.state.load(Ordering::Acquire) == READY {
+        let fd = SAVED.fd.load(Ordering::Relaxed);
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, (*SAVED.ios.get()).as_ptr()) };
+    }
+    // The signal is blocked while its handler runs, so it is delivered with the default action
+    // as soon as this returns
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+/// Signal handlers installed by [`RawTerminal::install_signal_restore`], uninstalled on drop.
+pub(crate) struct SignalRestore {
+    previous: Vec<(libc::c_int, libc::sigaction)>,
+}
+
+impl SignalRestore {
+    fn install(fd: libc::c_int, ios: &Termios, signals: &[Signal]) -> io::Result<Self> {
+        if SAVED
+            .state
+            .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "signal restore is already installed for another terminal",
+            ));
+        }
+        SAVED.fd.store(fd, Ordering::Relaxed);
+        // SAFETY: `state` is `WRITING`, so nothing else accesses `ios`
+        unsafe { (*SAVED.ios.get()).write(*ios) };
+        SAVED.state.store(READY, Ordering::Release);
+
+        // If installing a handler fails, dropping `restore` uninstalls the ones before it
+        let mut restore = SignalRestore {
+            previous: Vec::with_capacity(signals.len()),
+        };
+        for signal in signals {
+            let signal = signal.as_raw();
+            let previous = set_handler(signal, restore_and_reraise)?;
+            restore.previous.push((signal, previous));
+        }
+        Ok(restore)
+    }
+}
+
+impl Drop for SignalRestore {
+    fn drop(&mut self) {
+        for (signal, previous) in self.previous.iter().rev() {
+            let _ = reset_handler(*signal, previous);
+        }
+        SAVED.state.store(EMPTY, Ordering::Release);
+    }
+}
+
+impl<W: Write + AsFd> RawTerminal<W> {
+    /// Restore the terminal if the process is killed by one of `signals`.
+    ///
+    /// Destructors don't run when a signal terminates the process, so the terminal would be left
+    /// in raw mode. This installs handlers that restore the attributes saved by this
+    /// `RawTerminal` and then terminate the process with the signal's default action, as if no
+    /// handler had been installed.
+    ///
+    /// The handlers are uninstalled, and the previous ones put back, when this `RawTerminal` is
+    /// dropped or this is called again. Only one terminal at a time can install them; otherwise
+    /// an [`AlreadyExists`](io::ErrorKind::AlreadyExists) error is returned.
+    ///
+    /// ```rust,no_run
+    /// use termion_raw2::{IntoRawMode, Signal};
+    /// use std::io::stdout;
+    ///
+    /// let mut stdout = stdout().into_raw_mode()?;
+    /// stdout.install_signal_restore(&[Signal::Term, Signal::Hup])?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn install_signal_restore(&mut self, signals: &[Signal]) -> io::Result<()> {
+        self.signal_restore = None;
+        let fd = self.control_fd().as_raw_fd();
+        self.signal_restore = Some(SignalRestore::install(fd, self.restore_target(), signals)?);
+        Ok(())
+    }
+
+    /// Restore the terminal, as far as possible, if the process crashes.
+    ///
+    /// When the process aborts, e.g. on a panic while panicking or with `panic = "abort"`,
+    /// destructors don't run. This installs a `SIGABRT` handler restoring the attributes with a
+    /// single `tcsetattr`, after which the process aborts as usual. With `memory_faults`,
+    /// `SIGSEGV` and `SIGBUS` are handled the same way. This replaces the handler Rust uses to
+    /// report stack overflows, so the process then dies without that message.
+    ///
+    /// This is a shorthand for [`install_signal_restore`](Self::install_signal_restore) with
+    /// these signals, and replaces handlers installed by it. To handle other signals as well,
+    /// pass all of them to `install_signal_restore`.
+    ///
+    /// ```rust,no_run
+    /// use termion_raw2::IntoRawMode;
+    /// use std::io::stdout;
+    ///
+    /// let mut stdout = stdout().into_raw_mode()?;
+    /// stdout.install_last_resort_restore(true)?;
+    /// std::process::abort();
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn install_last_resort_restore(&mut self, memory_faults: bool) -> io::Result<()> {
+        if memory_faults {
+            self.install_signal_restore(&[Signal::Abort, Signal::Segv, Signal::Bus])
+        } else {
+            self.install_signal_restore(&[Signal::Abort])
+        }
+    }
+}
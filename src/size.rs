@@ -0,0 +1,110 @@
+//! Querying the terminal size.
+
+use std::{
+    io::{self, Write},
+    os::fd::AsFd,
+};
+
+use crate::sys::tty::win_size;
+use crate::RawTerminal;
+
+/// The size of a terminal window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WinSize {
+    /// Width in character cells.
+    pub cols: u16,
+    /// Height in character cells.
+    pub rows: u16,
+    /// Width in pixels, if reported.
+    pub width_px: Option<u16>,
+    /// Height in pixels, if reported.
+    pub height_px: Option<u16>,
+}
+
+impl From<libc::winsize> for WinSize {
+    fn from(size: libc::winsize) -> Self {
+        // Terminals that don't know their pixel size report 0
+        let pixels = |px| Some(px).filter(|&px| px != 0);
+        WinSize {
+            cols: size.ws_col,
+            rows: size.ws_row,
+            width_px: pixels(size.ws_xpixel),
+            height_px: pixels(size.ws_ypixel),
+        }
+    }
+}
+
+impl<W: Write + AsFd> RawTerminal<W> {
+    /// The window size of the terminal (`TIOCGWINSZ`)
+    ///
+    /// Many terminals don't report their size in pixels, so `width_px` and `height_px` are
+    /// frequently `None`.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let size = libc::winsize { ws_row: 24, ws_col: 80, ws_xpixel: 0, ws_ypixel: 0 };
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), &size) };
+    /// # assert_eq!(ret, 0);
+    /// # let tty = unsafe { File::from_raw_fd(slave) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// let tty = tty.into_raw_mode()?;
+    /// let size = tty.win_size()?;
+    /// assert_eq!((size.cols, size.rows), (80, 24));
+    /// assert_eq!(size.width_px, None);
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn win_size(&self) -> io::Result<WinSize> {
+        win_size(self.control_fd()).map(WinSize::from)
+    }
+
+    /// The size of the terminal as `(cols, rows)`
+    pub fn size(&self) -> io::Result<(u16, u16)> {
+        self.win_size().map(|size| (size.cols, size.rows))
+    }
+
+    /// The size of the terminal as `(cols, rows)`, falling back to the environment or `default`
+    ///
+    /// The size comes from the first of these that provides one:
+    ///
+    /// 1. the `TIOCGWINSZ` ioctl, unless it fails or reports a width or height of 0, as some
+    ///    pseudo terminals and serial lines do,
+    /// 2. the `COLUMNS` and `LINES` environment variables, each on its own,
+    /// 3. `default`.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let tty = unsafe { File::from_raw_fd(slave) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// // A new pseudo terminal has a size of 0x0
+    /// let tty = tty.into_raw_mode()?;
+    /// std::env::set_var("COLUMNS", "132");
+    /// std::env::remove_var("LINES");
+    /// assert_eq!(tty.size_with_fallback((80, 24)), (132, 24));
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn size_with_fallback(&self, default: (u16, u16)) -> (u16, u16) {
+        match self.size() {
+            Ok((cols, rows)) if cols > 0 && rows > 0 => (cols, rows),
+            _ => (
+                env_size("COLUMNS").unwrap_or(default.0),
+                env_size("LINES").unwrap_or(default.1),
+            ),
+        }
+    }
+}
+
+/// A positive size from the environment variable `name`.
+fn env_size(name: &str) -> Option<u16> {
+    std::env::var(name)
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&n| n > 0)
+}
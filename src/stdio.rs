@@ -0,0 +1,73 @@
+//! Raw mode for the standard streams.
+
+use std::{
+    fs::File,
+    io::{self, Read, Stdin, Stdout},
+    os::fd::{AsFd, BorrowedFd},
+};
+
+use crate::{IntoRawMode, RawTerminal, RestoreGuard};
+
+/// Standard output in raw mode, as returned by [`raw_stdout`].
+pub type RawStdout = RawTerminal<Stdout>;
+
+/// Switch the terminal behind standard output to raw mode.
+///
+/// This is `io::stdout().into_raw_mode()`.
+///
+/// ```rust,no_run
+/// use std::io::Write;
+///
+/// let mut stdout = termion_raw2::raw_stdout()?;
+/// write!(stdout, "Hey there.\r\n")?;
+/// # std::io::Result::Ok(())
+/// ```
+pub fn raw_stdout() -> io::Result<RawStdout> {
+    io::stdout().into_raw_mode()
+}
+
+/// Standard input, with its terminal in raw mode until this is dropped.
+///
+/// Returned by [`raw_stdin`], for programs that only read from the terminal, e.g. because their
+/// standard output is redirected. Reading goes through [`Stdin`], so it can be combined with
+/// [`RawInput`](crate::RawInput) for reading keys.
+#[derive(Debug)]
+pub struct RawStdin {
+    stdin: Stdin,
+    _guard: RestoreGuard,
+}
+
+/// Switch the terminal behind standard input to raw mode.
+///
+/// The attributes from before are restored when the returned [`RawStdin`] is dropped.
+///
+/// ```rust,no_run
+/// use std::io::Read;
+///
+/// let mut stdin = termion_raw2::raw_stdin()?;
+/// let mut key = [0];
+/// stdin.read_exact(&mut key)?;
+/// # std::io::Result::Ok(())
+/// ```
+pub fn raw_stdin() -> io::Result<RawStdin> {
+    let stdin = io::stdin();
+    // Raw mode is entered through a writer, so use a duplicate of the fd as one
+    let fd = File::from(stdin.as_fd().try_clone_to_owned()?);
+    let (_, guard) = fd.into_raw_mode()?.split()?;
+    Ok(RawStdin {
+        stdin,
+        _guard: guard,
+    })
+}
+
+impl Read for RawStdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stdin.read(buf)
+    }
+}
+
+impl AsFd for RawStdin {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.stdin.as_fd()
+    }
+}
@@ -0,0 +1,134 @@
+//! Sharing a raw terminal between threads.
+
+use std::{
+    fmt,
+    io::{self, Write},
+    os::fd::AsFd,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use crate::RawTerminal;
+
+/// A [`RawTerminal`] behind a mutex, to be shared between threads.
+///
+/// Writes, [`suspend_raw_mode`](Self::suspend_raw_mode) and
+/// [`activate_raw_mode`](Self::activate_raw_mode) are serialized, so a thread never writes while
+/// another one is switching modes. A single [`write_all`](Write::write_all) or
+/// [`write_fmt`](Write::write_fmt) holds the lock for its whole duration, so its output isn't
+/// interleaved with that of other threads.
+///
+/// Restoring happens when the wrapper is dropped, which needs ownership and therefore can't
+/// overlap with any other operation. Share it in an [`Arc`](std::sync::Arc) to have the last
+/// thread done with it restore the terminal. A thread panicking while holding the lock doesn't
+/// prevent the others from using the terminal, or the terminal from being restored.
+///
+/// ```rust
+/// # use std::{fs::File, io::Read, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # assert_eq!(ret, 0);
+/// # let (mut master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// use termion_raw2::{IntoRawMode, SyncRawTerminal};
+/// use std::{io::Write, sync::Arc, thread};
+///
+/// let term = Arc::new(SyncRawTerminal::new(tty.into_raw_mode()?));
+/// let writers: Vec<_> = (0..4u8)
+///     .map(|i| {
+///         let term = Arc::clone(&term);
+///         thread::spawn(move || {
+///             for _ in 0..100 {
+///                 (&*term).write_all(&[b'a' + i; 3])?;
+///                 if i == 0 {
+///                     term.suspend_raw_mode()?;
+///                     term.activate_raw_mode()?;
+///                 }
+///             }
+///             std::io::Result::Ok(())
+///         })
+///     })
+///     .collect();
+/// for writer in writers {
+///     writer.join().unwrap()?;
+/// }
+/// let mut out = vec![0; 4 * 100 * 3];
+/// master.read_exact(&mut out)?;
+/// // Each write arrived in one piece
+/// assert!(out.chunks(3).all(|chunk| chunk[1..].iter().all(|&b| b == chunk[0])));
+/// # std::io::Result::Ok(())
+/// ```
+pub struct SyncRawTerminal<W: Write + AsFd> {
+    inner: Mutex<RawTerminal<W>>,
+}
+
+impl<W: Write + AsFd> SyncRawTerminal<W> {
+    /// Wrap `term`.
+    pub fn new(term: RawTerminal<W>) -> Self {
+        SyncRawTerminal {
+            inner: Mutex::new(term),
+        }
+    }
+
+    /// Lock the terminal for exclusive use by the current thread.
+    pub fn lock(&self) -> MutexGuard<'_, RawTerminal<W>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Temporarily switch to original mode, see [`RawTerminal::suspend_raw_mode`].
+    pub fn suspend_raw_mode(&self) -> io::Result<()> {
+        self.lock().suspend_raw_mode()
+    }
+
+    /// Temporarily switch to raw mode, see [`RawTerminal::activate_raw_mode`].
+    pub fn activate_raw_mode(&self) -> io::Result<()> {
+        self.lock().activate_raw_mode()
+    }
+
+    /// Unwrap the terminal.
+    pub fn into_inner(self) -> RawTerminal<W> {
+        self.inner
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<W: Write + AsFd> fmt::Debug for SyncRawTerminal<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncRawTerminal").finish_non_exhaustive()
+    }
+}
+
+impl<W: Write + AsFd> Write for &SyncRawTerminal<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.lock().write_all(buf)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        self.lock().write_fmt(args)
+    }
+}
+
+impl<W: Write + AsFd> Write for SyncRawTerminal<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self).flush()
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        (&*self).write_all(buf)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        (&*self).write_fmt(args)
+    }
+}
@@ -8,22 +8,661 @@ pub mod attr {
         os::fd::{AsRawFd, BorrowedFd},
     };
 
+    #[cfg(all(not(any(miri, test)), feature = "nix-backend"))]
+    use nix::sys::termios;
+
     use super::{cvt, Termios};
 
+    /// The terminal attribute operations, done through `libc` or, under Miri and in unit tests,
+    /// simulated.
+    pub trait TermiosBackend {
+        fn get(fd: BorrowedFd) -> io::Result<Termios>;
+        fn set(fd: BorrowedFd, mode: SetMode, termios: &Termios) -> io::Result<()>;
+        fn make_raw(termios: &mut Termios);
+        fn speeds(termios: &Termios) -> (libc::speed_t, libc::speed_t);
+        fn set_speeds(termios: &mut Termios, speed: libc::speed_t) -> io::Result<()>;
+    }
+
+    #[cfg(not(any(miri, test, feature = "nix-backend")))]
+    type Backend = Libc;
+    #[cfg(all(not(any(miri, test)), feature = "nix-backend"))]
+    type Backend = Nix;
+    #[cfg(any(miri, test))]
+    type Backend = super::sim::Simulated;
+
+    #[cfg(not(any(miri, test, feature = "nix-backend")))]
+    pub struct Libc;
+
+    #[cfg(not(any(miri, test, feature = "nix-backend")))]
+    impl TermiosBackend for Libc {
+        fn get(fd: BorrowedFd) -> io::Result<Termios> {
+            unsafe {
+                let mut termios = mem::zeroed();
+                cvt(libc::tcgetattr(fd.as_raw_fd(), &mut termios))?;
+                Ok(termios)
+            }
+        }
+
+        fn set(fd: BorrowedFd, mode: SetMode, termios: &Termios) -> io::Result<()> {
+            let mode = match mode {
+                SetMode::Now => libc::TCSANOW,
+                SetMode::Drain => libc::TCSADRAIN,
+                SetMode::Flush => libc::TCSAFLUSH,
+            };
+            cvt(unsafe { libc::tcsetattr(fd.as_raw_fd(), mode, termios) }).and(Ok(()))
+        }
+
+        fn make_raw(termios: &mut Termios) {
+            unsafe { libc::cfmakeraw(termios) }
+        }
+
+        fn speeds(termios: &Termios) -> (libc::speed_t, libc::speed_t) {
+            unsafe { (libc::cfgetispeed(termios), libc::cfgetospeed(termios)) }
+        }
+
+        fn set_speeds(termios: &mut Termios, speed: libc::speed_t) -> io::Result<()> {
+            cvt(unsafe { libc::cfsetispeed(termios, speed) })?;
+            cvt(unsafe { libc::cfsetospeed(termios, speed) }).and(Ok(()))
+        }
+    }
+
+    /// The terminal attribute operations done through `nix`, with the `nix-backend` feature.
+    #[cfg(all(not(any(miri, test)), feature = "nix-backend"))]
+    pub struct Nix;
+
+    #[cfg(all(not(any(miri, test)), feature = "nix-backend"))]
+    impl TermiosBackend for Nix {
+        fn get(fd: BorrowedFd) -> io::Result<Termios> {
+            Ok(termios::tcgetattr(fd)?.into())
+        }
+
+        fn set(fd: BorrowedFd, mode: SetMode, ios: &Termios) -> io::Result<()> {
+            let mode = match mode {
+                SetMode::Now => termios::SetArg::TCSANOW,
+                SetMode::Drain => termios::SetArg::TCSADRAIN,
+                SetMode::Flush => termios::SetArg::TCSAFLUSH,
+            };
+            Ok(termios::tcsetattr(fd, mode, &termios::Termios::from(*ios))?)
+        }
+
+        fn make_raw(ios: &mut Termios) {
+            let mut converted = termios::Termios::from(*ios);
+            termios::cfmakeraw(&mut converted);
+            *ios = converted.into();
+        }
+
+        fn speeds(ios: &Termios) -> (libc::speed_t, libc::speed_t) {
+            let converted = termios::Termios::from(*ios);
+            (
+                termios::cfgetispeed(&converted) as libc::speed_t,
+                termios::cfgetospeed(&converted) as libc::speed_t,
+            )
+        }
+
+        fn set_speeds(ios: &mut Termios, speed: libc::speed_t) -> io::Result<()> {
+            // The BSDs take any speed as a number, elsewhere it has to be one of `BaudRate`
+            #[cfg(any(
+                target_os = "freebsd",
+                target_os = "dragonfly",
+                target_os = "netbsd",
+                target_os = "openbsd",
+                target_vendor = "apple"
+            ))]
+            let speed = speed as u32;
+            #[cfg(not(any(
+                target_os = "freebsd",
+                target_os = "dragonfly",
+                target_os = "netbsd",
+                target_os = "openbsd",
+                target_vendor = "apple"
+            )))]
+            let speed = termios::BaudRate::try_from(speed)?;
+            let mut converted = termios::Termios::from(*ios);
+            termios::cfsetispeed(&mut converted, speed)?;
+            termios::cfsetospeed(&mut converted, speed)?;
+            *ios = converted.into();
+            Ok(())
+        }
+    }
+
     pub fn get_terminal_attr(fd: BorrowedFd) -> io::Result<Termios> {
-        unsafe {
-            let mut termios = mem::zeroed();
-            cvt(libc::tcgetattr(fd.as_raw_fd(), &mut termios))?;
-            Ok(termios)
+        let result = Backend::get(fd);
+        #[cfg(feature = "log")]
+        match &result {
+            Ok(ios) => log::debug!("tcgetattr(fd {}): {}", fd.as_raw_fd(), Describe(ios)),
+            Err(e) => log::debug!("tcgetattr(fd {}) failed: {e}", fd.as_raw_fd()),
         }
+        result
     }
 
     pub fn set_terminal_attr(fd: BorrowedFd, termios: &Termios) -> io::Result<()> {
-        cvt(unsafe { libc::tcsetattr(fd.as_raw_fd(), libc::TCSANOW, termios) }).and(Ok(()))
+        set_terminal_attr_with(fd, SetMode::Now, termios)
+    }
+
+    /// Set the attributes unless they are already set, returning whether they were changed.
+    pub fn set_terminal_attr_if_changed(fd: BorrowedFd, termios: &Termios) -> io::Result<bool> {
+        if attrs_equal(&get_terminal_attr(fd)?, termios) {
+            return Ok(false);
+        }
+        set_terminal_attr(fd, termios)?;
+        Ok(true)
+    }
+
+    /// Whether `a` and `b` have the same flags, control characters and speeds.
+    ///
+    /// The structs can't be compared as a whole, as their padding and reserved fields may
+    /// contain anything.
+    pub fn attrs_equal(a: &Termios, b: &Termios) -> bool {
+        a.c_iflag == b.c_iflag
+            && a.c_oflag == b.c_oflag
+            && a.c_cflag == b.c_cflag
+            && a.c_lflag == b.c_lflag
+            && a.c_cc == b.c_cc
+            && Backend::speeds(a) == Backend::speeds(b)
+    }
+
+    /// When new terminal attributes take effect.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    pub enum SetMode {
+        /// Immediately (`TCSANOW`).
+        #[default]
+        Now,
+        /// After all pending output has been transmitted (`TCSADRAIN`).
+        Drain,
+        /// After all pending output has been transmitted, discarding all input that hasn't been
+        /// read yet (`TCSAFLUSH`).
+        Flush,
+    }
+
+    pub fn set_terminal_attr_with(
+        fd: BorrowedFd,
+        mode: SetMode,
+        termios: &Termios,
+    ) -> io::Result<()> {
+        let result = Backend::set(fd, mode, termios);
+        #[cfg(feature = "log")]
+        match &result {
+            Ok(()) => log::debug!(
+                "tcsetattr(fd {}, {mode:?}): {}",
+                fd.as_raw_fd(),
+                Describe(termios)
+            ),
+            Err(e) => log::debug!(
+                "tcsetattr(fd {}, {mode:?}) failed: {e}, attributes {}",
+                fd.as_raw_fd(),
+                Describe(termios)
+            ),
+        }
+        result
+    }
+
+    /// Formats the flags and the read settings of attributes for the debug log.
+    #[cfg(feature = "log")]
+    struct Describe<'a>(&'a Termios);
+
+    #[cfg(feature = "log")]
+    impl std::fmt::Display for Describe<'_> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let ios = self.0;
+            let on = |flag| if ios.c_lflag & flag != 0 { "on" } else { "off" };
+            write!(
+                f,
+                "iflag {:#x} oflag {:#x} cflag {:#x} lflag {:#x} (ICANON {}, ECHO {}, ISIG {}), \
+                 VMIN {} VTIME {}",
+                ios.c_iflag,
+                ios.c_oflag,
+                ios.c_cflag,
+                ios.c_lflag,
+                on(libc::ICANON),
+                on(libc::ECHO),
+                on(libc::ISIG),
+                ios.c_cc[libc::VMIN],
+                ios.c_cc[libc::VTIME],
+            )
+        }
     }
 
     pub fn raw_terminal_attr(termios: &mut Termios) {
-        unsafe { libc::cfmakeraw(termios) }
+        Backend::make_raw(termios)
+    }
+
+    /// The output speed of the terminal, as a `speed_t`.
+    pub fn output_speed(fd: BorrowedFd) -> io::Result<libc::speed_t> {
+        Ok(Backend::speeds(&get_terminal_attr(fd)?).1)
+    }
+
+    /// Set the input and output speed in `termios`.
+    pub fn set_speeds(termios: &mut Termios, speed: libc::speed_t) -> io::Result<()> {
+        Backend::set_speeds(termios, speed)
+    }
+
+    /// Read the output speed set with [`set_custom_baud`].
+    #[cfg(all(
+        target_os = "linux",
+        not(any(target_arch = "powerpc", target_arch = "powerpc64"))
+    ))]
+    pub fn custom_baud(fd: BorrowedFd) -> io::Result<u32> {
+        let mut tio: libc::termios2 = unsafe { mem::zeroed() };
+        cvt(unsafe { libc::ioctl(fd.as_raw_fd(), libc::TCGETS2, &mut tio) })?;
+        Ok(tio.c_ospeed)
+    }
+
+    /// Set an arbitrary input and output speed with `termios2` and `BOTHER`.
+    #[cfg(all(
+        target_os = "linux",
+        not(any(target_arch = "powerpc", target_arch = "powerpc64"))
+    ))]
+    pub fn set_custom_baud(fd: BorrowedFd, baud: u32) -> io::Result<()> {
+        const CBAUD: libc::tcflag_t = libc::CBAUD as libc::tcflag_t;
+        const BOTHER: libc::tcflag_t = libc::BOTHER as libc::tcflag_t;
+
+        let mut tio: libc::termios2 = unsafe { mem::zeroed() };
+        cvt(unsafe { libc::ioctl(fd.as_raw_fd(), libc::TCGETS2, &mut tio) })?;
+        tio.c_cflag &= !(CBAUD | CBAUD << libc::IBSHIFT);
+        tio.c_cflag |= BOTHER | BOTHER << libc::IBSHIFT;
+        tio.c_ispeed = baud;
+        tio.c_ospeed = baud;
+        cvt(unsafe { libc::ioctl(fd.as_raw_fd(), libc::TCSETS2, &tio) }).and(Ok(()))
+    }
+
+    // The flags cleared by `cfmakeraw` (as in glibc and musl)
+    const RAW_IFLAG: libc::tcflag_t = libc::IGNBRK
+        | libc::BRKINT
+        | libc::PARMRK
+        | libc::ISTRIP
+        | libc::INLCR
+        | libc::IGNCR
+        | libc::ICRNL
+        | libc::IXON;
+    const RAW_OFLAG: libc::tcflag_t = libc::OPOST;
+    const RAW_LFLAG: libc::tcflag_t =
+        libc::ECHO | libc::ECHONL | libc::ICANON | libc::ISIG | libc::IEXTEN;
+    const RAW_CFLAG: libc::tcflag_t = libc::CSIZE | libc::PARENB;
+
+    /// What `cfmakeraw` does, in plain Rust.
+    pub fn make_raw(ios: &mut Termios) {
+        ios.c_iflag &= !RAW_IFLAG;
+        ios.c_oflag &= !RAW_OFLAG;
+        ios.c_lflag &= !RAW_LFLAG;
+        ios.c_cflag &= !RAW_CFLAG;
+        ios.c_cflag |= libc::CS8;
+        ios.c_cc[libc::VMIN] = 1;
+        ios.c_cc[libc::VTIME] = 0;
+    }
+
+    /// Whether `a` and `b` agree on everything `cfmakeraw` changes.
+    pub fn raw_attrs_match(a: &Termios, b: &Termios) -> bool {
+        a.c_iflag & RAW_IFLAG == b.c_iflag & RAW_IFLAG
+            && a.c_oflag & RAW_OFLAG == b.c_oflag & RAW_OFLAG
+            && a.c_lflag & RAW_LFLAG == b.c_lflag & RAW_LFLAG
+            && a.c_cflag & RAW_CFLAG == b.c_cflag & RAW_CFLAG
+            && a.c_cc[libc::VMIN] == b.c_cc[libc::VMIN]
+            && a.c_cc[libc::VTIME] == b.c_cc[libc::VTIME]
+    }
+}
+
+/// An in-memory model of terminal attributes, so that the logic built on them runs under Miri,
+/// which can't call into `libc`, and unit tests can count the calls an operation takes.
+///
+/// Every fd is treated as a terminal, starting out with typical cooked mode attributes. Only the
+/// attribute operations are simulated; everything else still needs a real terminal.
+#[cfg(any(miri, test))]
+pub(crate) mod sim {
+    use std::{
+        cell::Cell,
+        io, mem,
+        os::fd::{AsRawFd, BorrowedFd, RawFd},
+        sync::{Mutex, PoisonError},
+    };
+
+    use super::attr::{make_raw, SetMode, TermiosBackend};
+    use super::Termios;
+
+    static TERMINALS: Mutex<Vec<(RawFd, Termios)>> = Mutex::new(Vec::new());
+
+    thread_local! {
+        /// How many times this thread read and set attributes, as `(gets, sets)`
+        static CALLS: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+    }
+
+    /// How many times the attributes were read and set so far on this thread, as
+    /// `(gets, sets)`.
+    #[cfg(test)]
+    pub fn call_counts() -> (usize, usize) {
+        CALLS.get()
+    }
+
+    pub struct Simulated;
+
+    impl TermiosBackend for Simulated {
+        fn get(fd: BorrowedFd) -> io::Result<Termios> {
+            CALLS.set((CALLS.get().0 + 1, CALLS.get().1));
+            let terminals = TERMINALS.lock().unwrap_or_else(PoisonError::into_inner);
+            Ok(terminals
+                .iter()
+                .find(|(raw, _)| *raw == fd.as_raw_fd())
+                .map_or_else(cooked, |&(_, ios)| ios))
+        }
+
+        fn set(fd: BorrowedFd, _mode: SetMode, termios: &Termios) -> io::Result<()> {
+            CALLS.set((CALLS.get().0, CALLS.get().1 + 1));
+            let mut terminals = TERMINALS.lock().unwrap_or_else(PoisonError::into_inner);
+            match terminals.iter_mut().find(|(raw, _)| *raw == fd.as_raw_fd()) {
+                Some((_, ios)) => *ios = *termios,
+                None => terminals.push((fd.as_raw_fd(), *termios)),
+            }
+            Ok(())
+        }
+
+        fn make_raw(ios: &mut Termios) {
+            make_raw(ios)
+        }
+
+        fn speeds(_: &Termios) -> (libc::speed_t, libc::speed_t) {
+            // Speeds aren't simulated
+            (0, 0)
+        }
+
+        fn set_speeds(_: &mut Termios, _: libc::speed_t) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Typical attributes of a terminal in cooked mode.
+    fn cooked() -> Termios {
+        let mut ios: Termios = unsafe { mem::zeroed() };
+        ios.c_iflag = libc::ICRNL | libc::IXON;
+        ios.c_oflag = libc::OPOST | libc::ONLCR;
+        ios.c_cflag = libc::CS8 | libc::CREAD;
+        ios.c_lflag =
+            libc::ISIG | libc::ICANON | libc::ECHO | libc::ECHOE | libc::ECHOK | libc::IEXTEN;
+        ios.c_cc[libc::VMIN] = 1;
+        ios
+    }
+}
+
+pub mod poll {
+    use std::{
+        io,
+        os::fd::{AsRawFd, BorrowedFd},
+        time::{Duration, Instant},
+    };
+
+    use super::cvt;
+
+    /// Wait until `fd` is readable, returning `false` if `timeout` elapses first.
+    pub fn wait_readable(fd: BorrowedFd, timeout: Option<Duration>) -> io::Result<bool> {
+        wait_for(fd, libc::POLLIN, timeout)
+    }
+
+    /// Wait until `fd` is writable, returning `false` if `timeout` elapses first.
+    pub fn wait_writable(fd: BorrowedFd, timeout: Option<Duration>) -> io::Result<bool> {
+        wait_for(fd, libc::POLLOUT, timeout)
+    }
+
+    /// Wait until `a` or `b` is readable, returning which of them are.
+    #[cfg(feature = "input")]
+    pub fn wait_readable_either(a: BorrowedFd, b: BorrowedFd) -> io::Result<(bool, bool)> {
+        let mut pollfds = [a, b].map(|fd| libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        });
+        loop {
+            match cvt(unsafe { libc::poll(pollfds.as_mut_ptr(), 2, -1) }) {
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        // Hangups and errors count as readable, so that the read reports them
+        let ready = |pollfd: &libc::pollfd| pollfd.revents != 0;
+        Ok((ready(&pollfds[0]), ready(&pollfds[1])))
+    }
+
+    fn wait_for(
+        fd: BorrowedFd,
+        events: libc::c_short,
+        timeout: Option<Duration>,
+    ) -> io::Result<bool> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let timeout_ms = match deadline {
+                None => -1,
+                Some(deadline) => {
+                    // Round up, so that a sub-millisecond remainder doesn't become a busy loop
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    remaining
+                        .as_nanos()
+                        .div_ceil(1_000_000)
+                        .min(libc::c_int::MAX as u128) as libc::c_int
+                }
+            };
+            let mut pollfd = libc::pollfd {
+                fd: fd.as_raw_fd(),
+                events,
+                revents: 0,
+            };
+            match cvt(unsafe { libc::poll(&mut pollfd, 1, timeout_ms) }) {
+                Ok(ready) => return Ok(ready > 0),
+                // Interrupted by a signal, retry with whatever time is left
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+pub mod tty {
+    use std::{
+        ffi::{CStr, OsStr},
+        io,
+        os::{
+            fd::{AsRawFd, BorrowedFd},
+            unix::ffi::OsStrExt,
+        },
+        path::PathBuf,
+    };
+
+    use super::cvt;
+
+    /// The path of the terminal device behind `fd`.
+    pub fn tty_name(fd: BorrowedFd) -> io::Result<PathBuf> {
+        let mut buf = vec![0u8; 64];
+        loop {
+            // `ttyname_r` returns the error number instead of setting `errno`
+            let ret =
+                unsafe { libc::ttyname_r(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+            match ret {
+                0 => {
+                    let name = CStr::from_bytes_until_nul(&buf).map_err(io::Error::other)?;
+                    return Ok(PathBuf::from(OsStr::from_bytes(name.to_bytes())));
+                }
+                libc::ERANGE if buf.len() < 64 * 1024 => buf.resize(buf.len() * 2, 0),
+                err => return Err(io::Error::from_raw_os_error(err)),
+            }
+        }
+    }
+
+    /// The window size of the terminal behind `fd`.
+    pub fn win_size(fd: BorrowedFd) -> io::Result<libc::winsize> {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        cvt(unsafe { libc::ioctl(fd.as_raw_fd(), libc::TIOCGWINSZ, &mut size) })?;
+        Ok(size)
+    }
+
+    /// Whether `fd` refers to a terminal.
+    pub fn is_tty(fd: BorrowedFd) -> bool {
+        unsafe { libc::isatty(fd.as_raw_fd()) == 1 }
+    }
+
+    /// The device and inode of the file behind `fd` (`st_dev`, `st_ino`), which identify it.
+    pub fn file_id(fd: BorrowedFd) -> io::Result<(libc::dev_t, libc::ino_t)> {
+        let mut stat = unsafe { std::mem::zeroed::<libc::stat>() };
+        cvt(unsafe { libc::fstat(fd.as_raw_fd(), &mut stat) })?;
+        Ok((stat.st_dev, stat.st_ino))
+    }
+
+    /// The device number of the file behind `fd` (`st_rdev`), which identifies a terminal.
+    pub fn device(fd: BorrowedFd) -> io::Result<libc::dev_t> {
+        let mut stat = unsafe { std::mem::zeroed::<libc::stat>() };
+        cvt(unsafe { libc::fstat(fd.as_raw_fd(), &mut stat) })?;
+        Ok(stat.st_rdev)
+    }
+
+    /// The foreground process group of the terminal behind `fd`.
+    pub fn foreground_pgrp(fd: BorrowedFd) -> io::Result<libc::pid_t> {
+        cvt(unsafe { libc::tcgetpgrp(fd.as_raw_fd()) })
+    }
+
+    /// The line discipline of the terminal behind `fd` (`TIOCGETD`).
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn line_discipline(fd: BorrowedFd) -> io::Result<libc::c_int> {
+        let mut ldisc: libc::c_int = 0;
+        cvt(unsafe { libc::ioctl(fd.as_raw_fd(), libc::TIOCGETD, &mut ldisc) })?;
+        Ok(ldisc)
+    }
+
+    /// Set the line discipline of the terminal behind `fd` (`TIOCSETD`).
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn set_line_discipline(fd: BorrowedFd, ldisc: libc::c_int) -> io::Result<()> {
+        cvt(unsafe { libc::ioctl(fd.as_raw_fd(), libc::TIOCSETD, &ldisc) }).and(Ok(()))
+    }
+
+    /// The number of bytes that can be read from `fd` without blocking.
+    pub fn bytes_available(fd: BorrowedFd) -> io::Result<usize> {
+        let mut available: libc::c_int = 0;
+        cvt(unsafe { libc::ioctl(fd.as_raw_fd(), libc::FIONREAD, &mut available) })?;
+        Ok(available as usize)
+    }
+}
+
+#[cfg(feature = "input")]
+pub mod pipe {
+    use std::{
+        io,
+        os::fd::{FromRawFd, OwnedFd},
+    };
+
+    use super::cvt;
+
+    /// Create a close-on-exec pipe, returning `(read, write)`.
+    ///
+    /// With `nonblocking`, both ends are non-blocking, so that a signal handler can write to it
+    /// to wake up a `poll` on the read end.
+    pub fn pipe(nonblocking: bool) -> io::Result<(OwnedFd, OwnedFd)> {
+        let mut fds = [0; 2];
+        cvt(unsafe { libc::pipe(fds.as_mut_ptr()) })?;
+        // SAFETY: `pipe` just opened these
+        let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        for fd in fds {
+            unsafe {
+                if nonblocking {
+                    let flags = cvt(libc::fcntl(fd, libc::F_GETFL))?;
+                    cvt(libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK))?;
+                }
+                cvt(libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC))?;
+            }
+        }
+        Ok((read, write))
+    }
+}
+
+pub mod signal {
+    use std::{io, mem};
+
+    use super::cvt;
+
+    /// Install `handler` for `signal`, returning the previous action.
+    pub fn set_handler(
+        signal: libc::c_int,
+        handler: extern "C" fn(libc::c_int),
+    ) -> io::Result<libc::sigaction> {
+        unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = handler as libc::sighandler_t;
+            // Run on the alternate stack if there is one, so that a stack overflow can be handled,
+            // and restart interrupted calls, so that blocking reads and writes elsewhere in the
+            // program don't fail with `EINTR` whenever the signal arrives
+            action.sa_flags = libc::SA_ONSTACK | libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            let mut old = mem::zeroed();
+            cvt(libc::sigaction(signal, &action, &mut old))?;
+            Ok(old)
+        }
+    }
+
+    /// Run `f` without changing `errno`, as signal handlers must.
+    #[cfg(feature = "input")]
+    pub fn preserving_errno<F: FnOnce()>(f: F) {
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "emscripten",
+            target_os = "redox",
+            target_os = "hurd"
+        ))]
+        let errno = unsafe { libc::__errno_location() };
+        #[cfg(any(
+            target_vendor = "apple",
+            target_os = "freebsd",
+            target_os = "dragonfly"
+        ))]
+        let errno = unsafe { libc::__error() };
+        #[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+        let errno = unsafe { libc::__errno() };
+        #[cfg(any(target_os = "illumos", target_os = "solaris"))]
+        let errno = unsafe { libc::___errno() };
+        #[cfg(target_os = "haiku")]
+        let errno = unsafe { libc::_errnop() };
+        #[cfg(target_os = "aix")]
+        let errno = unsafe { libc::_Errno() };
+        // Elsewhere `errno` can't be reached in an async-signal-safe way, so it is left as `f`
+        // leaves it
+        #[cfg(not(any(
+            target_os = "linux",
+            target_os = "emscripten",
+            target_os = "redox",
+            target_os = "hurd",
+            target_vendor = "apple",
+            target_os = "freebsd",
+            target_os = "dragonfly",
+            target_os = "android",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "haiku",
+            target_os = "aix"
+        )))]
+        let errno = std::ptr::null_mut::<libc::c_int>();
+
+        if errno.is_null() {
+            return f();
+        }
+        let saved = unsafe { *errno };
+        f();
+        unsafe { *errno = saved };
+    }
+
+    /// Put back an action returned by [`set_handler`].
+    pub fn reset_handler(signal: libc::c_int, action: &libc::sigaction) -> io::Result<()> {
+        cvt(unsafe { libc::sigaction(signal, action, std::ptr::null_mut()) }).and(Ok(()))
     }
 }
 
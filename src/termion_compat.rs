@@ -0,0 +1,145 @@
+//! A drop-in replacement for the `raw` module of [`termion`](https://docs.rs/termion).
+//!
+//! termion always controls the terminal through stdout, whatever the writer is, and only
+//! requires the writer to implement [`Write`]. The types in this module do the same, on top of
+//! this crate's [`RawTerminal`](crate::RawTerminal), so code written against termion compiles
+//! unchanged:
+//!
+//! ```rust,no_run
+//! use termion_raw2::termion_compat::IntoRawMode;
+//! use std::io::{Write, stdout};
+//!
+//! let mut stdout = stdout().into_raw_mode()?;
+//! write!(stdout, "Hey there.").unwrap();
+//! # std::io::Result::Ok(())
+//! ```
+
+use std::{
+    io::{self, Stdout, Write},
+    ops,
+    os::fd::{AsRawFd, RawFd},
+};
+
+/// A terminal restorer, which keeps the previous state of the terminal, and restores it, when
+/// dropped.
+///
+/// Restoring will entirely bring back the old TTY state.
+///
+/// On drop, the output is flushed before the terminal is restored, so output still buffered in
+/// it is written in raw mode, as it was meant to be:
+///
+/// ```rust
+/// # use std::{fs::File, os::fd::{AsRawFd, FromRawFd}, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # assert_eq!(ret, 0);
+/// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// # // termion controls the terminal through stdout, so make the pseudo terminal stdout
+/// # assert_eq!(unsafe { libc::dup2(tty.as_raw_fd(), 1) }, 1);
+/// use termion_raw2::{is_raw, termion_compat::IntoRawMode};
+/// use std::{cell::Cell, io::{self, BufWriter, Write}, rc::Rc};
+///
+/// /// Records whether the terminal was raw when the output reached it
+/// struct Probe<'a>(&'a File, Rc<Cell<Option<bool>>>);
+///
+/// impl Write for Probe<'_> {
+///     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+///         self.1.set(Some(is_raw(self.0)?));
+///         self.0.write(buf)
+///     }
+///
+///     fn flush(&mut self) -> io::Result<()> {
+///         self.0.flush()
+///     }
+/// }
+///
+/// let raw = Rc::new(Cell::new(None));
+/// let mut out = BufWriter::new(Probe(&tty, raw.clone())).into_raw_mode()?;
+/// write!(out, "Hey there.")?;
+/// drop(out);
+/// assert_eq!(raw.get(), Some(true));
+/// assert!(!is_raw(&tty)?);
+/// # std::io::Result::Ok(())
+/// ```
+pub struct RawTerminal<W: Write> {
+    output: W,
+    guard: crate::RawTerminal<Stdout>,
+}
+
+/// Flushes the output, then restores the terminal. Errors are ignored.
+impl<W: Write> Drop for RawTerminal<W> {
+    fn drop(&mut self) {
+        let _ = self.output.flush();
+        let _ = self.guard.restore();
+    }
+}
+
+/// A raw mode stdout, the most common case.
+pub type RawStdout = RawTerminal<Stdout>;
+
+impl<W: Write> ops::Deref for RawTerminal<W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        &self.output
+    }
+}
+
+impl<W: Write> ops::DerefMut for RawTerminal<W> {
+    fn deref_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+}
+
+impl<W: Write> Write for RawTerminal<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+impl<W: Write + AsRawFd> AsRawFd for RawTerminal<W> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.output.as_raw_fd()
+    }
+}
+
+/// Types which can be converted into "raw mode".
+///
+/// # Why is this type defined on writers and not readers?
+///
+/// TTYs has their state controlled by the writer, not the reader. You use the writer to clear the
+/// screen, move the cursor and so on, so naturally you use the writer to change the mode as well.
+pub trait IntoRawMode: Write + Sized {
+    /// Switch to raw mode.
+    ///
+    /// Raw mode means that stdin won't be printed (it will instead have to be written manually by
+    /// the program). Furthermore, the input isn't canonicalised or buffered (that is, you can
+    /// read from stdin one byte of a time). The output is neither modified in any way.
+    fn into_raw_mode(self) -> io::Result<RawTerminal<Self>>;
+}
+
+impl<W: Write> IntoRawMode for W {
+    fn into_raw_mode(self) -> io::Result<RawTerminal<W>> {
+        let guard = crate::IntoRawMode::into_raw_mode(io::stdout())?;
+        Ok(RawTerminal {
+            output: self,
+            guard,
+        })
+    }
+}
+
+impl<W: Write> RawTerminal<W> {
+    /// Temporarily switch to original mode
+    pub fn suspend_raw_mode(&self) -> io::Result<()> {
+        self.guard.suspend_raw_mode()
+    }
+
+    /// Temporarily switch to raw mode
+    pub fn activate_raw_mode(&self) -> io::Result<()> {
+        self.guard.activate_raw_mode()
+    }
+}
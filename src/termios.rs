@@ -0,0 +1,423 @@
+//! Typed views over parts of the terminal attributes.
+
+use std::{fmt, ops};
+
+use libc::{cc_t, tcflag_t};
+
+use crate::sys::{
+    attr::{attrs_equal, make_raw},
+    Termios,
+};
+
+/// A copy of a terminal's attributes at some point in time.
+///
+/// Snapshots compare equal if the flags, the control characters and the speeds are equal.
+/// Padding and reserved fields of the underlying struct are ignored, as they may contain
+/// anything.
+///
+/// ```rust
+/// # use std::{fs::File, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # assert_eq!(ret, 0);
+/// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// use termion_raw2::IntoRawMode;
+///
+/// let (before, raw) = tty.preview()?;
+/// let (unchanged, _) = tty.preview()?;
+/// assert_eq!(before, unchanged);
+/// assert_ne!(before, raw);
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Clone, Copy)]
+pub struct TermiosSnapshot(Termios);
+
+impl TermiosSnapshot {
+    /// The raw attributes.
+    pub fn as_termios(&self) -> &Termios {
+        &self.0
+    }
+
+    /// The attributes with raw mode applied, as [`IntoRawMode::into_raw_mode`] would.
+    ///
+    /// This is the transformation done by `cfmakeraw` in glibc and musl, computed in plain Rust
+    /// so that it doesn't need a terminal. The `cfmakeraw` of other C libraries, like those of
+    /// the BSDs and macOS, clears a few more flags.
+    ///
+    /// ```rust
+    /// # use std::{fs::File, os::fd::FromRawFd, ptr};
+    /// # let (mut master, mut slave) = (0, 0);
+    /// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+    /// # assert_eq!(ret, 0);
+    /// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    /// use termion_raw2::IntoRawMode;
+    ///
+    /// let (current, raw) = tty.preview()?;
+    /// # if cfg!(target_os = "linux") {
+    /// assert_eq!(current.to_raw(), raw);
+    /// # }
+    /// # std::io::Result::Ok(())
+    /// ```
+    ///
+    /// [`IntoRawMode::into_raw_mode`]: crate::IntoRawMode::into_raw_mode
+    pub fn to_raw(&self) -> TermiosSnapshot {
+        let mut ios = self.0;
+        make_raw(&mut ios);
+        TermiosSnapshot(ios)
+    }
+
+    /// The input processing flags.
+    pub fn input_flags(&self) -> InputFlags {
+        InputFlags::from_bits_retain(self.0.c_iflag)
+    }
+
+    /// The output processing flags.
+    pub fn output_flags(&self) -> OutputFlags {
+        OutputFlags::from_bits_retain(self.0.c_oflag)
+    }
+
+    /// The local flags.
+    pub fn local_flags(&self) -> LocalFlags {
+        LocalFlags::from_bits_retain(self.0.c_lflag)
+    }
+
+    /// The hardware control flags.
+    pub fn control_flags(&self) -> ControlFlags {
+        ControlFlags::from_bits_retain(self.0.c_cflag)
+    }
+
+    /// The special control characters.
+    pub fn control_chars(&self) -> ControlChars {
+        ControlChars::from_termios(&self.0)
+    }
+}
+
+impl From<Termios> for TermiosSnapshot {
+    fn from(ios: Termios) -> Self {
+        TermiosSnapshot(ios)
+    }
+}
+
+impl PartialEq for TermiosSnapshot {
+    fn eq(&self, other: &Self) -> bool {
+        attrs_equal(&self.0, &other.0)
+    }
+}
+
+impl Eq for TermiosSnapshot {}
+
+impl fmt::Debug for TermiosSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TermiosSnapshot")
+            .field("iflag", &format_args!("{:#x}", self.0.c_iflag))
+            .field("oflag", &format_args!("{:#x}", self.0.c_oflag))
+            .field("cflag", &format_args!("{:#x}", self.0.c_cflag))
+            .field("lflag", &format_args!("{:#x}", self.0.c_lflag))
+            .field("cc", &self.control_chars())
+            .finish()
+    }
+}
+
+/// The special control characters of a terminal (`c_cc`).
+///
+/// Each field holds the byte that triggers the corresponding action, or
+/// [`ControlChars::DISABLED`] if the action is disabled. `min` and `time` are not characters but
+/// the `VMIN`/`VTIME` settings for non-canonical reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlChars {
+    /// Sends `SIGINT` (`VINTR`, usually Ctrl-C).
+    pub intr: cc_t,
+    /// Sends `SIGQUIT` (`VQUIT`, usually Ctrl-\\).
+    pub quit: cc_t,
+    /// Erases the previous character (`VERASE`, usually DEL or Ctrl-H).
+    pub erase: cc_t,
+    /// Erases the current line (`VKILL`, usually Ctrl-U).
+    pub kill: cc_t,
+    /// Signals end of input (`VEOF`, usually Ctrl-D).
+    pub eof: cc_t,
+    /// Resumes output (`VSTART`, usually Ctrl-Q).
+    pub start: cc_t,
+    /// Pauses output (`VSTOP`, usually Ctrl-S).
+    pub stop: cc_t,
+    /// Sends `SIGTSTP` (`VSUSP`, usually Ctrl-Z).
+    pub susp: cc_t,
+    /// Minimum number of bytes for a non-canonical read (`VMIN`).
+    pub min: cc_t,
+    /// Timeout for a non-canonical read in tenths of a second (`VTIME`).
+    pub time: cc_t,
+}
+
+impl ControlChars {
+    /// The value that disables a control character (`_POSIX_VDISABLE`).
+    pub const DISABLED: cc_t = libc::_POSIX_VDISABLE;
+
+    pub(crate) fn from_termios(ios: &Termios) -> Self {
+        Self {
+            intr: ios.c_cc[libc::VINTR],
+            quit: ios.c_cc[libc::VQUIT],
+            erase: ios.c_cc[libc::VERASE],
+            kill: ios.c_cc[libc::VKILL],
+            eof: ios.c_cc[libc::VEOF],
+            start: ios.c_cc[libc::VSTART],
+            stop: ios.c_cc[libc::VSTOP],
+            susp: ios.c_cc[libc::VSUSP],
+            min: ios.c_cc[libc::VMIN],
+            time: ios.c_cc[libc::VTIME],
+        }
+    }
+
+    pub(crate) fn apply_to(&self, ios: &mut Termios) {
+        ios.c_cc[libc::VINTR] = self.intr;
+        ios.c_cc[libc::VQUIT] = self.quit;
+        ios.c_cc[libc::VERASE] = self.erase;
+        ios.c_cc[libc::VKILL] = self.kill;
+        ios.c_cc[libc::VEOF] = self.eof;
+        ios.c_cc[libc::VSTART] = self.start;
+        ios.c_cc[libc::VSTOP] = self.stop;
+        ios.c_cc[libc::VSUSP] = self.susp;
+        ios.c_cc[libc::VMIN] = self.min;
+        ios.c_cc[libc::VTIME] = self.time;
+    }
+}
+
+/// Implements the set operations shared by the flag types.
+macro_rules! impl_flags {
+    ($name:ident) => {
+        impl $name {
+            /// No flags set.
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            /// The raw bits of the flags.
+            pub const fn bits(self) -> tcflag_t {
+                self.0
+            }
+
+            /// Create flags from raw bits, keeping bits that have no named constant.
+            pub const fn from_bits_retain(bits: tcflag_t) -> Self {
+                Self(bits)
+            }
+
+            /// Whether all flags in `other` are set.
+            pub const fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            /// Set the flags in `other`.
+            pub fn insert(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+
+            /// Clear the flags in `other`.
+            pub fn remove(&mut self, other: Self) {
+                self.0 &= !other.0;
+            }
+
+            /// Set or clear the flags in `other`.
+            pub fn set(&mut self, other: Self, value: bool) {
+                if value {
+                    self.insert(other);
+                } else {
+                    self.remove(other);
+                }
+            }
+        }
+
+        impl ops::BitOr for $name {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self {
+                Self(self.0 | rhs.0)
+            }
+        }
+
+        impl ops::BitOrAssign for $name {
+            fn bitor_assign(&mut self, rhs: Self) {
+                self.0 |= rhs.0;
+            }
+        }
+
+        impl ops::BitAnd for $name {
+            type Output = Self;
+
+            fn bitand(self, rhs: Self) -> Self {
+                Self(self.0 & rhs.0)
+            }
+        }
+
+        impl ops::BitAndAssign for $name {
+            fn bitand_assign(&mut self, rhs: Self) {
+                self.0 &= rhs.0;
+            }
+        }
+
+        impl ops::Not for $name {
+            type Output = Self;
+
+            fn not(self) -> Self {
+                Self(!self.0)
+            }
+        }
+    };
+}
+
+/// Input processing flags (`c_iflag`).
+///
+/// Raw mode clears `IGNBRK`, `BRKINT`, `PARMRK`, `ISTRIP`, `INLCR`, `IGNCR`, `ICRNL` and `IXON`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputFlags(tcflag_t);
+
+impl InputFlags {
+    /// Ignore break conditions.
+    pub const IGNBRK: Self = Self(libc::IGNBRK);
+    /// Flush the queues and send `SIGINT` on a break condition, unless `IGNBRK` is set.
+    pub const BRKINT: Self = Self(libc::BRKINT);
+    /// Ignore bytes with framing or parity errors.
+    pub const IGNPAR: Self = Self(libc::IGNPAR);
+    /// Mark bytes with parity errors, unless `IGNPAR` is set: such a byte `b` is read as
+    /// `\xff \0 b`, and a valid `\xff` as `\xff \xff`. Only applies when `INPCK` is set.
+    pub const PARMRK: Self = Self(libc::PARMRK);
+    /// Check the parity of input bytes.
+    pub const INPCK: Self = Self(libc::INPCK);
+    /// Strip input bytes to 7 bits.
+    pub const ISTRIP: Self = Self(libc::ISTRIP);
+    /// Map `\n` to `\r`.
+    pub const INLCR: Self = Self(libc::INLCR);
+    /// Ignore `\r`.
+    pub const IGNCR: Self = Self(libc::IGNCR);
+    /// Map `\r` to `\n`, unless `IGNCR` is set.
+    pub const ICRNL: Self = Self(libc::ICRNL);
+    /// Software flow control of the output: the `stop` and `start` characters pause and resume
+    /// it.
+    pub const IXON: Self = Self(libc::IXON);
+    /// With `IXON`, any character resumes paused output.
+    pub const IXANY: Self = Self(libc::IXANY);
+    /// Software flow control of the input: send the `stop` and `start` characters when the input
+    /// queue is close to full and has room again.
+    pub const IXOFF: Self = Self(libc::IXOFF);
+}
+
+impl_flags!(InputFlags);
+
+/// Output processing flags (`c_oflag`).
+///
+/// Raw mode clears all of them. Keeping `OPOST | ONLCR` lets the kernel expand `\n` to `\r\n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutputFlags(tcflag_t);
+
+impl OutputFlags {
+    /// Enable implementation-defined output processing. The other flags only apply if this is
+    /// set.
+    pub const OPOST: Self = Self(libc::OPOST);
+    /// Map `\n` to `\r\n`.
+    pub const ONLCR: Self = Self(libc::ONLCR);
+    /// Map `\r` to `\n`.
+    pub const OCRNL: Self = Self(libc::OCRNL);
+    /// Don't output `\r` at column 0.
+    pub const ONOCR: Self = Self(libc::ONOCR);
+    /// `\n` also performs the carriage return function.
+    pub const ONLRET: Self = Self(libc::ONLRET);
+}
+
+impl_flags!(OutputFlags);
+
+/// Hardware control flags (`c_cflag`), mostly relevant for serial lines.
+///
+/// The character size occupies several bits and is accessed through
+/// [`char_size`](Self::char_size) and [`set_char_size`](Self::set_char_size).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ControlFlags(tcflag_t);
+
+impl ControlFlags {
+    /// Use two stop bits instead of one.
+    pub const CSTOPB: Self = Self(libc::CSTOPB);
+    /// Enable the receiver.
+    pub const CREAD: Self = Self(libc::CREAD);
+    /// Enable parity generation on output and parity checking on input.
+    pub const PARENB: Self = Self(libc::PARENB);
+    /// Use odd parity instead of even.
+    pub const PARODD: Self = Self(libc::PARODD);
+    /// Hang up (lower the modem control lines) after the last close.
+    pub const HUPCL: Self = Self(libc::HUPCL);
+    /// Ignore modem control lines.
+    pub const CLOCAL: Self = Self(libc::CLOCAL);
+
+    /// The number of bits per character.
+    pub fn char_size(self) -> CharSize {
+        match self.0 & libc::CSIZE {
+            libc::CS5 => CharSize::Five,
+            libc::CS6 => CharSize::Six,
+            libc::CS7 => CharSize::Seven,
+            _ => CharSize::Eight,
+        }
+    }
+
+    /// Set the number of bits per character.
+    pub fn set_char_size(&mut self, size: CharSize) {
+        let bits = match size {
+            CharSize::Five => libc::CS5,
+            CharSize::Six => libc::CS6,
+            CharSize::Seven => libc::CS7,
+            CharSize::Eight => libc::CS8,
+        };
+        self.0 = (self.0 & !libc::CSIZE) | bits;
+    }
+}
+
+impl_flags!(ControlFlags);
+
+/// The number of bits per character (`CSIZE`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CharSize {
+    /// 5 bits (`CS5`).
+    Five,
+    /// 6 bits (`CS6`).
+    Six,
+    /// 7 bits (`CS7`).
+    Seven,
+    /// 8 bits (`CS8`), which raw mode sets.
+    Eight,
+}
+
+/// Local flags (`c_lflag`), controlling echo, line editing and signals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalFlags(tcflag_t);
+
+impl LocalFlags {
+    /// Generate signals for the `intr`, `quit` and `susp` characters.
+    pub const ISIG: Self = Self(libc::ISIG);
+    /// Canonical mode: line buffering and line editing.
+    pub const ICANON: Self = Self(libc::ICANON);
+    /// Echo input characters.
+    pub const ECHO: Self = Self(libc::ECHO);
+    /// In canonical mode, the `erase` character erases the previous character on screen.
+    pub const ECHOE: Self = Self(libc::ECHOE);
+    /// In canonical mode, the `kill` character erases the current line on screen.
+    pub const ECHOK: Self = Self(libc::ECHOK);
+    /// In canonical mode, echo `\n` even if `ECHO` is off.
+    pub const ECHONL: Self = Self(libc::ECHONL);
+    /// Don't flush the queues when generating signals.
+    pub const NOFLSH: Self = Self(libc::NOFLSH);
+    /// Enable implementation-defined input processing, like Ctrl-V for literal input.
+    pub const IEXTEN: Self = Self(libc::IEXTEN);
+    /// Send `SIGTTOU` to background processes that write to the terminal, stopping them by
+    /// default.
+    pub const TOSTOP: Self = Self(libc::TOSTOP);
+    /// With `ECHO`, show control characters as `^X`, e.g. `^C`.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "solaris",
+        target_os = "illumos",
+    ))]
+    pub const ECHOCTL: Self = Self(libc::ECHOCTL);
+}
+
+impl_flags!(LocalFlags);
@@ -0,0 +1,58 @@
+//! Proof that raw mode is active, checked by the borrow checker.
+
+use std::{io::Write, marker::PhantomData, os::fd::AsFd};
+
+use crate::RawTerminal;
+
+/// Evidence that a [`RawTerminal`] is alive, borrowed from it with [`RawTerminal::token`].
+///
+/// The token is zero-sized and can only be created by borrowing a `RawTerminal`, so it can't
+/// outlive the guard which restores the terminal. Functions which need raw mode can take a
+/// `RawToken<'_>` parameter, making it impossible to call them without an active guard:
+///
+/// ```rust
+/// # use std::{fs::File, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # assert_eq!(ret, 0);
+/// # let (_master, tty) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+/// use termion_raw2::{IntoRawMode, RawToken};
+///
+/// fn needs_raw_mode(_: RawToken<'_>) {}
+///
+/// let tty = tty.into_raw_mode()?;
+/// needs_raw_mode(tty.token());
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// The token borrows the terminal, so it can't be kept after the terminal is dropped:
+///
+/// ```rust,compile_fail
+/// # use std::{fs::File, os::fd::FromRawFd, ptr};
+/// # let (mut master, mut slave) = (0, 0);
+/// # let ret = unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) };
+/// # let tty = unsafe { File::from_raw_fd(slave) };
+/// use termion_raw2::IntoRawMode;
+///
+/// let tty = tty.into_raw_mode()?;
+/// let token = tty.token();
+/// drop(tty);
+/// let _ = token;
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// The token only proves that the terminal hasn't been restored for good. It doesn't track
+/// [`RawTerminal::suspend_raw_mode`], which only takes a shared reference.
+#[derive(Debug, Clone, Copy)]
+pub struct RawToken<'a> {
+    _terminal: PhantomData<&'a ()>,
+}
+
+impl<W: Write + AsFd> RawTerminal<W> {
+    /// Borrow a [`RawToken`] as evidence that raw mode is active
+    pub fn token(&self) -> RawToken<'_> {
+        RawToken {
+            _terminal: PhantomData,
+        }
+    }
+}
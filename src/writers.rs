@@ -0,0 +1,676 @@
+//! Writer adapters for output in raw mode.
+
+use std::{
+    fmt,
+    io::{self, Write},
+    num::NonZeroU32,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A writer translating each lone `\n` into `\r\n`.
+///
+/// In raw mode the output isn't processed, so `\n` only moves the cursor down without returning
+/// it to the start of the line. This adapter restores the familiar behavior. A `\n` that is
+/// already preceded by `\r` is left alone, also when the two end up in separate writes.
+///
+/// ```rust
+/// use termion_raw2::CrlfWriter;
+/// use std::io::Write;
+///
+/// let mut out = CrlfWriter::new(Vec::new());
+/// out.write_all(b"one\ntwo\r")?;
+/// out.write_all(b"\nthree\n")?;
+/// assert_eq!(out.into_inner(), b"one\r\ntwo\r\nthree\r\n");
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct CrlfWriter<W: Write> {
+    inner: W,
+    after_cr: bool,
+}
+
+impl<W: Write> CrlfWriter<W> {
+    /// Wrap `inner`.
+    pub fn new(inner: W) -> Self {
+        CrlfWriter {
+            inner,
+            after_cr: false,
+        }
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn write_plain(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if written > 0 {
+            self.after_cr = buf[written - 1] == b'\r';
+        }
+        Ok(written)
+    }
+}
+
+impl<W: Write> Write for CrlfWriter<W> {
+    /// Returns the number of bytes of `buf` consumed, which doesn't count the inserted `\r`s.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let lone_newline = buf.iter().enumerate().position(|(i, &byte)| {
+            let after_cr = if i == 0 {
+                self.after_cr
+            } else {
+                buf[i - 1] == b'\r'
+            };
+            byte == b'\n' && !after_cr
+        });
+        match lone_newline {
+            Some(0) => {
+                // `write_plain` keeps `after_cr` up to date, so that a retry after a failure
+                // between the two bytes doesn't repeat the `\r`
+                let mut crlf = &b"\r\n"[..];
+                while !crlf.is_empty() {
+                    match self.write_plain(crlf)? {
+                        0 => return Err(io::ErrorKind::WriteZero.into()),
+                        n => crlf = &crlf[n..],
+                    }
+                }
+                Ok(1)
+            }
+            Some(i) => self.write_plain(&buf[..i]),
+            None => self.write_plain(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer flushing after every write.
+///
+/// Writers that buffer, like [`BufWriter`](io::BufWriter), hold back output until their buffer
+/// fills up or they are flushed. In raw mode, where the program echoes every keystroke itself,
+/// this shows up as output appearing late. This adapter flushes the inner writer after each
+/// [`write`](Write::write), and once after a whole [`write_all`](Write::write_all) or
+/// [`write_fmt`](Write::write_fmt).
+///
+/// Flushing after every write means a system call for every write, which costs throughput when
+/// writing a lot of output at once. Turn flushing off with [`set_enabled`](Self::set_enabled)
+/// for such bursts, so that writes are coalesced by the inner writer again, and turn it back on
+/// for interactive output.
+///
+/// ```rust
+/// use termion_raw2::AutoFlush;
+/// use std::io::{BufWriter, Write};
+///
+/// let mut out = AutoFlush::new(BufWriter::new(Vec::new()));
+/// out.write_all(b"typed")?;
+/// assert_eq!(out.get_ref().get_ref(), b"typed");
+///
+/// out.set_enabled(false);
+/// out.write_all(b" more")?;
+/// assert_eq!(out.get_ref().get_ref(), b"typed");
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct AutoFlush<W: Write> {
+    inner: W,
+    enabled: bool,
+}
+
+impl<W: Write> AutoFlush<W> {
+    /// Wrap `inner`, with flushing enabled.
+    pub fn new(inner: W) -> Self {
+        AutoFlush {
+            inner,
+            enabled: true,
+        }
+    }
+
+    /// Turn flushing after every write on or off.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Whether flushing after every write is on.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn flush_if_enabled(&mut self) -> io::Result<()> {
+        if self.enabled {
+            self.inner.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for AutoFlush<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.flush_if_enabled()?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.inner.write_all(buf)?;
+        self.flush_if_enabled()
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        self.inner.write_fmt(args)?;
+        self.flush_if_enabled()
+    }
+}
+
+/// A writer pacing its output to a number of bytes per second.
+///
+/// Over a slow serial line, writing a whole screen at once can overrun the terminal. This adapter
+/// writes at most a hundredth of a second's worth of bytes per call, and sleeps before each write
+/// until the bytes written so far are due at the configured rate. After a pause, pacing starts
+/// over, so idle time doesn't allow a burst. With no rate set, writes go straight through.
+///
+/// Pacing only works if the inner writer doesn't buffer; flushing just flushes the inner writer.
+///
+/// ```rust
+/// use termion_raw2::ThrottledWriter;
+/// use std::{io::Write, num::NonZeroU32, time::{Duration, Instant}};
+///
+/// let mut out = ThrottledWriter::new(Vec::new(), NonZeroU32::new(1000));
+/// let start = Instant::now();
+/// out.write_all(&[b'x'; 100])?;
+/// // The last of the ten chunks of 10 bytes is due 90 ms in
+/// assert!(start.elapsed() >= Duration::from_millis(80));
+/// assert_eq!(out.into_inner().len(), 100);
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct ThrottledWriter<W: Write> {
+    inner: W,
+    rate: Option<NonZeroU32>,
+    /// When the current stretch of paced output started
+    start: Instant,
+    /// The bytes written since `start`
+    sent: u64,
+}
+
+impl<W: Write> ThrottledWriter<W> {
+    /// Wrap `inner`, writing at most `bytes_per_second`, or without a limit if `None`.
+    pub fn new(inner: W, bytes_per_second: Option<NonZeroU32>) -> Self {
+        ThrottledWriter {
+            inner,
+            rate: bytes_per_second,
+            start: Instant::now(),
+            sent: 0,
+        }
+    }
+
+    /// Change the rate, or remove the limit with `None`.
+    pub fn set_rate(&mut self, bytes_per_second: Option<NonZeroU32>) {
+        self.rate = bytes_per_second;
+        self.sent = 0;
+    }
+
+    /// The rate in bytes per second, if limited.
+    pub fn rate(&self) -> Option<NonZeroU32> {
+        self.rate
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ThrottledWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(rate) = self.rate else {
+            return self.inner.write(buf);
+        };
+        let rate = u64::from(rate.get());
+        let now = Instant::now();
+        let due = self.start + Duration::from_secs_f64(self.sent as f64 / rate as f64);
+        if due > now {
+            thread::sleep(due - now);
+        } else {
+            self.start = now;
+            self.sent = 0;
+        }
+        let chunk = (rate / 100).max(1) as usize;
+        let written = self.inner.write(&buf[..buf.len().min(chunk)])?;
+        self.sent += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer delivering its output a line at a time.
+///
+/// Output is buffered until a write contains a `\n` or `\r`. Everything up to and including the
+/// last one is then written to the inner writer, which is flushed, and the rest stays buffered
+/// for the next line. This batches the bytes of a line into one system call without delaying
+/// complete lines, a middle ground between no buffering and [`AutoFlush`].
+///
+/// A line longer than the buffer capacity is written out as the buffer fills up, without
+/// flushing. An unterminated last line is written and flushed by [`flush`](Write::flush),
+/// [`into_inner`](Self::into_inner) or on drop, where errors are ignored.
+///
+/// ```rust
+/// use termion_raw2::LineFlushWriter;
+/// use std::io::Write;
+///
+/// let mut out = LineFlushWriter::new(Vec::new());
+/// out.write_all(b"one\ntw")?;
+/// assert_eq!(out.get_ref(), b"one\n");
+/// out.write_all(b"o")?;
+/// assert_eq!(out.get_ref(), b"one\n");
+/// assert_eq!(out.into_inner()?, b"one\ntwo");
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct LineFlushWriter<W: Write> {
+    /// Only `None` once `into_inner` took it out
+    inner: Option<W>,
+    /// The start of the current line
+    buf: Vec<u8>,
+    capacity: usize,
+}
+
+impl<W: Write> LineFlushWriter<W> {
+    /// Wrap `inner`, with a buffer of the default capacity.
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(1024, inner)
+    }
+
+    /// Wrap `inner`, buffering at most `capacity` bytes of a line.
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        LineFlushWriter {
+            inner: Some(inner),
+            buf: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// The bytes of the current line, not written to the inner writer yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect("inner writer taken")
+    }
+
+    /// Get a mutable reference to the inner writer.
+    ///
+    /// Writing to it directly puts the output before any buffered bytes.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().expect("inner writer taken")
+    }
+
+    /// Write and flush the buffered bytes, and unwrap the inner writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.inner.take().expect("inner writer taken"))
+    }
+
+    fn write_buf(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            let inner = self.inner.as_mut().expect("inner writer taken");
+            inner.write_all(&self.buf)?;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for LineFlushWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (lines, rest) = match buf.iter().rposition(|&b| b == b'\n' || b == b'\r') {
+            Some(end) => buf.split_at(end + 1),
+            None => (&[][..], buf),
+        };
+        if !lines.is_empty() {
+            self.write_buf()?;
+            self.get_mut().write_all(lines)?;
+            self.get_mut().flush()?;
+        }
+        if self.buf.len() + rest.len() > self.capacity {
+            // Once the lines are out, report them as written; the error comes up again on the
+            // next call
+            let written = self.write_buf().and_then(|()| {
+                if rest.len() > self.capacity {
+                    self.get_mut().write_all(rest)?;
+                    return Ok(true);
+                }
+                Ok(false)
+            });
+            match written {
+                Ok(true) => return Ok(buf.len()),
+                Ok(false) => {}
+                Err(_) if !lines.is_empty() => return Ok(lines.len()),
+                Err(e) => return Err(e),
+            }
+        }
+        self.buf.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_buf()?;
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for LineFlushWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.flush();
+        }
+    }
+}
+
+/// A writer estimating the cursor position from the bytes written.
+///
+/// The position is 1-based `(row, col)`, like in escape sequences, and starts at `(1, 1)`, or
+/// wherever [`set_position`](Self::set_position) puts it. These bytes are understood:
+///
+/// - printable characters, which move one column right,
+/// - `\r`, `\n` (without moving to the first column, as output isn't processed in raw mode),
+///   backspace and tab,
+/// - the CSI sequences moving the cursor (`A` to `H`, `d`, `f`), and saving and restoring it
+///   (`ESC 7`/`ESC 8`, `CSI s`/`CSI u`).
+///
+/// Other escape sequences, including ones with intermediate bytes like `ESC ( B` and strings
+/// (OSC, DCS, SOS, PM and APC), are skipped without moving the cursor. With
+/// the terminal size set by [`set_size`](Self::set_size), lines wrap at the last column the way
+/// xterm does, and the row stays on the last line when the screen scrolls.
+///
+/// This is an estimate: every character is assumed to be one column wide, so wide and combining
+/// characters put it off, and so do sequences it doesn't know that move the cursor, the user
+/// resizing the terminal, or other programs writing to it.
+///
+/// ```rust
+/// use termion_raw2::TrackingWriter;
+/// use std::io::Write;
+///
+/// let mut out = TrackingWriter::new(Vec::new());
+/// out.write_all("hello\r\nwörld".as_bytes())?;
+/// assert_eq!(out.position(), (2, 6));
+/// out.write_all(b"\x1b[10;3H\x1b[31mred\x1b[0m")?;
+/// assert_eq!(out.position(), (10, 6));
+/// // `tput sgr0`, and a DCS string
+/// out.write_all(b"\x1b(B\x1b[m\x1bPq#0\x1b\\ok")?;
+/// assert_eq!(out.position(), (10, 8));
+/// // An `ESC` in the middle of a sequence starts a new one
+/// out.write_all(b"\x1b[3\x1b[5;5H")?;
+/// assert_eq!(out.position(), (5, 5));
+///
+/// out.set_size(Some((4, 24)));
+/// out.set_position(1, 1);
+/// out.write_all(b"wrapped")?;
+/// assert_eq!(out.position(), (2, 4));
+/// // Restoring the cursor cancels the pending wrap after the last column
+/// out.write_all(b"\r\x1b7abcd\x1b8x")?;
+/// assert_eq!(out.position(), (2, 2));
+/// # std::io::Result::Ok(())
+/// ```
+#[cfg(feature = "sequences")]
+#[derive(Debug)]
+pub struct TrackingWriter<W: Write> {
+    inner: W,
+    row: u16,
+    col: u16,
+    saved: (u16, u16),
+    /// `(cols, rows)`, if known
+    size: Option<(u16, u16)>,
+    /// A character was written to the last column; the next one wraps first
+    pending_wrap: bool,
+    state: Tracking,
+    /// Parameter bytes of the current CSI sequence
+    params: Vec<u8>,
+}
+
+/// Where `TrackingWriter` is in parsing the output.
+#[cfg(feature = "sequences")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tracking {
+    Ground,
+    Esc,
+    /// After the intermediate bytes of an escape sequence like `ESC ( B`
+    EscIntermediate,
+    Csi,
+    /// In an OSC string, `true` right after an `ESC`
+    Osc(bool),
+    /// In a DCS, SOS, PM or APC string, which only `ST` ends, `true` right after an `ESC`
+    Str(bool),
+}
+
+#[cfg(feature = "sequences")]
+impl<W: Write> TrackingWriter<W> {
+    /// Wrap `inner`, with the cursor at `(1, 1)` and no known size.
+    pub fn new(inner: W) -> Self {
+        TrackingWriter {
+            inner,
+            row: 1,
+            col: 1,
+            saved: (1, 1),
+            size: None,
+            pending_wrap: false,
+            state: Tracking::Ground,
+            params: Vec::new(),
+        }
+    }
+
+    /// The estimated cursor position, as 1-based `(row, col)`.
+    pub fn position(&self) -> (u16, u16) {
+        (self.row, self.col)
+    }
+
+    /// Set the position, e.g. after querying the terminal for it.
+    pub fn set_position(&mut self, row: u16, col: u16) {
+        self.row = row.max(1);
+        self.col = col.max(1);
+        self.pending_wrap = false;
+    }
+
+    /// Set the terminal size as `(cols, rows)`, to track wrapping and scrolling.
+    pub fn set_size(&mut self, size: Option<(u16, u16)>) {
+        self.size = size;
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner writer.
+    ///
+    /// Writing to it directly isn't tracked.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn track(&mut self, byte: u8) {
+        match self.state {
+            Tracking::Ground => self.track_ground(byte),
+            Tracking::Esc => {
+                self.state = match byte {
+                    b'[' => {
+                        self.params.clear();
+                        Tracking::Csi
+                    }
+                    b']' => Tracking::Osc(false),
+                    b'P' | b'X' | b'^' | b'_' => Tracking::Str(false),
+                    0x20..=0x2f => Tracking::EscIntermediate,
+                    _ => {
+                        match byte {
+                            b'7' => self.saved = (self.row, self.col),
+                            b'8' => self.restore_position(),
+                            _ => {}
+                        }
+                        Tracking::Ground
+                    }
+                };
+            }
+            Tracking::EscIntermediate => {
+                if !(0x20..=0x2f).contains(&byte) {
+                    self.state = Tracking::Ground;
+                }
+            }
+            Tracking::Csi => match byte {
+                0x20..=0x3f => self.params.push(byte),
+                0x40..=0x7e => {
+                    self.state = Tracking::Ground;
+                    self.track_csi(byte);
+                }
+                0x1b => self.state = Tracking::Esc,
+                // Other bytes are ignored by terminals in the middle of a sequence too
+                _ => {}
+            },
+            Tracking::Osc(after_esc) => {
+                self.state = match byte {
+                    0x07 => Tracking::Ground,
+                    b'\\' if after_esc => Tracking::Ground,
+                    0x1b => Tracking::Osc(true),
+                    _ => Tracking::Osc(false),
+                };
+            }
+            Tracking::Str(after_esc) => {
+                self.state = match byte {
+                    b'\\' if after_esc => Tracking::Ground,
+                    0x1b => Tracking::Str(true),
+                    _ => Tracking::Str(false),
+                };
+            }
+        }
+    }
+
+    fn track_ground(&mut self, byte: u8) {
+        match byte {
+            0x1b => self.state = Tracking::Esc,
+            b'\r' => self.move_to(self.row, 1),
+            b'\n' => self.line_feed(),
+            0x08 => self.move_to(self.row, self.col.saturating_sub(1)),
+            b'\t' => self.move_to(self.row, (self.col - 1) / 8 * 8 + 9),
+            // Other control characters, and UTF-8 continuation bytes
+            0x00..=0x1f | 0x7f | 0x80..=0xbf => {}
+            _ => {
+                if self.pending_wrap {
+                    self.pending_wrap = false;
+                    self.line_feed();
+                    self.col = 1;
+                }
+                match self.size {
+                    Some((cols, _)) if self.col >= cols => self.pending_wrap = true,
+                    _ => self.col = self.col.saturating_add(1),
+                }
+            }
+        }
+    }
+
+    fn track_csi(&mut self, last: u8) {
+        let params = std::str::from_utf8(&self.params).unwrap_or("");
+        let mut numbers = params.split(';').map(|n| n.parse::<u16>().unwrap_or(0));
+        let mut next = || numbers.next().unwrap_or(0).max(1);
+        let (row, col) = (self.row, self.col);
+        match last {
+            b'A' => self.move_to(row.saturating_sub(next()), col),
+            b'B' => self.move_to(row.saturating_add(next()), col),
+            b'C' => self.move_to(row, col.saturating_add(next())),
+            b'D' => self.move_to(row, col.saturating_sub(next())),
+            b'E' => self.move_to(row.saturating_add(next()), 1),
+            b'F' => self.move_to(row.saturating_sub(next()), 1),
+            b'G' => self.move_to(row, next()),
+            b'd' => self.move_to(next(), col),
+            b'H' | b'f' => {
+                let row = next();
+                self.move_to(row, next());
+            }
+            b's' => self.saved = (row, col),
+            b'u' => self.restore_position(),
+            _ => {}
+        }
+    }
+
+    /// Move to a position, keeping it on the screen.
+    fn move_to(&mut self, row: u16, col: u16) {
+        let (cols, rows) = self.size.unwrap_or((u16::MAX, u16::MAX));
+        self.row = row.clamp(1, rows.max(1));
+        self.col = col.clamp(1, cols.max(1));
+        self.pending_wrap = false;
+    }
+
+    /// Move back to the position saved by `ESC 7` or `CSI s`.
+    fn restore_position(&mut self) {
+        let (row, col) = self.saved;
+        self.move_to(row, col);
+    }
+
+    fn line_feed(&mut self) {
+        self.move_to(self.row.saturating_add(1), self.col);
+    }
+}
+
+#[cfg(feature = "sequences")]
+impl<W: Write> Write for TrackingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        for &byte in &buf[..written] {
+            self.track(byte);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}